@@ -0,0 +1,452 @@
+//! Unique, typed IDs for the sprites of a rusty_engine game.
+//!
+//! rusty_engine tells sprites apart by label alone. Building labels by
+//! hand, as `format!("feris{}", n)`, and sorting them back out with
+//! `starts_with` goes wrong as soon as one kind's name is the start of
+//! another's. A [`Registry`] hands out an [`Id`] per sprite instead,
+//! numbered per kind and never reused, and each ID has exactly one
+//! label that reads back as the same ID.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display};
+
+/// The kinds of entity a game has.
+pub trait Kind: Copy + Ord + Debug + 'static {
+    /// Every kind, so labels can be read back.
+    const ALL: &'static [Self];
+
+    /// What the labels of this kind start with. Names must be distinct,
+    /// non-empty and not end in a digit.
+    fn name(self) -> &'static str;
+}
+
+/// One entity of kind `K`.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Id<K> {
+    kind: K,
+    number: u32,
+}
+
+impl<K: Kind> Id<K> {
+    pub fn kind(self) -> K {
+        self.kind
+    }
+
+    pub fn number(self) -> u32 {
+        self.number
+    }
+
+    /// The sprite label, the kind's name followed by the number.
+    pub fn label(self) -> String {
+        self.to_string()
+    }
+
+    /// The ID `label` was made from, if any. Numbers must be written
+    /// the way [`Id::label`] writes them, so `"feris01"` is no ID.
+    pub fn from_label(label: &str) -> Option<Self> {
+        K::ALL
+            .iter()
+            .find_map(
+                |&kind| {
+                    let digits =
+                        label.strip_prefix(kind.name())?;
+                    let canonical = !digits.is_empty()
+                        && digits
+                            .bytes()
+                            .all(|b| b.is_ascii_digit())
+                        && (digits == "0"
+                            || !digits.starts_with('0'));
+                    if !canonical {
+                        return None;
+                    }
+                    Some(Id {
+                        kind,
+                        number: digits
+                            .parse()
+                            .ok()?,
+                    })
+                },
+            )
+    }
+}
+
+impl<K: Kind> Display for Id<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.kind
+                .name(),
+            self.number
+        )
+    }
+}
+
+/// The live entities of a game.
+#[derive(Clone, Debug)]
+pub struct Registry<K: Kind> {
+    /// Number the next entity of each kind gets.
+    next: BTreeMap<K, u32>,
+    live: BTreeSet<Id<K>>,
+}
+
+impl<K: Kind> Default for Registry<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Kind> Registry<K> {
+    /// An empty registry.
+    ///
+    /// # Panics
+    ///
+    /// If the names of `K` could make two IDs with the same label.
+    pub fn new() -> Self {
+        for (i, &kind) in K::ALL
+            .iter()
+            .enumerate()
+        {
+            let name = kind.name();
+            assert!(
+                !name.is_empty()
+                    && !name.ends_with(
+                        |c: char| c.is_ascii_digit()
+                    ),
+                "{:?} has name {:?}",
+                kind,
+                name
+            );
+            assert!(
+                K::ALL[..i]
+                    .iter()
+                    .all(|other| other.name() != name),
+                "two kinds are named {:?}",
+                name
+            );
+        }
+        Self {
+            next: BTreeMap::new(),
+            live: BTreeSet::new(),
+        }
+    }
+
+    /// A new entity of `kind`.
+    ///
+    /// # Panics
+    ///
+    /// If every number of `kind` has been handed out.
+    pub fn spawn(&mut self, kind: K) -> Id<K> {
+        let next = self
+            .next
+            .entry(kind)
+            .or_default();
+        let id = Id {
+            kind,
+            number: *next,
+        };
+        *next = next
+            .checked_add(1)
+            .unwrap_or_else(
+                || panic!("ran out of {:?} numbers", kind),
+            );
+        self.live
+            .insert(id);
+        id
+    }
+
+    /// Take on `id`, made by another registry such as one that was
+    /// saved, so no later entity gets its number. Returns `false` if
+    /// it was already here, or if it has the last number so nothing
+    /// could come after it.
+    pub fn adopt(&mut self, id: Id<K>) -> bool {
+        let Some(after) = id
+            .number
+            .checked_add(1)
+        else {
+            return false;
+        };
+        let next = self
+            .next
+            .entry(id.kind)
+            .or_default();
+        *next = (*next).max(after);
+        self.live
+            .insert(id)
+    }
+
+    pub fn contains(&self, id: Id<K>) -> bool {
+        self.live
+            .contains(&id)
+    }
+
+    /// The live entity labelled `label`.
+    pub fn get(&self, label: &str) -> Option<Id<K>> {
+        Id::from_label(label)
+            .filter(|&id| self.contains(id))
+    }
+
+    /// The live entities of `kind`, oldest first.
+    pub fn of_kind(
+        &self,
+        kind: K,
+    ) -> impl Iterator<Item = Id<K>> + '_ {
+        self.live
+            .range(
+                Id { kind, number: 0 }..=Id {
+                    kind,
+                    number: u32::MAX,
+                },
+            )
+            .copied()
+    }
+
+    /// Every live entity, by kind and then oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = Id<K>> + '_ {
+        self.live
+            .iter()
+            .copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.live
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live
+            .is_empty()
+    }
+
+    /// Forget `id`. Returns `false` if it wasn't live.
+    pub fn remove(&mut self, id: Id<K>) -> bool {
+        self.live
+            .remove(&id)
+    }
+
+    /// Forget every entity of `kind`, returning them so their sprites
+    /// can go too.
+    pub fn remove_kind(&mut self, kind: K) -> Vec<Id<K>> {
+        let ids: Vec<_> = self
+            .of_kind(kind)
+            .collect();
+        for id in &ids {
+            self.live
+                .remove(id);
+        }
+        ids
+    }
+
+    /// Forget every entity, returning them so their sprites can go too.
+    /// Numbers carry on from where they were.
+    pub fn clear(&mut self) -> Vec<Id<K>> {
+        std::mem::take(&mut self.live)
+            .into_iter()
+            .collect()
+    }
+
+    /// Number the next entity of `kind` will get.
+    pub fn next(&self, kind: K) -> u32 {
+        self.next
+            .get(&kind)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Number the next entities of `kind` from `number`, as when
+    /// restoring a save. Numbers of live entities are never reused.
+    pub fn set_next(&mut self, kind: K, number: u32) {
+        let live = self
+            .of_kind(kind)
+            .last()
+            .map_or(
+                0,
+                |id| id.number.saturating_add(1),
+            );
+        self.next
+            .insert(
+                kind,
+                number.max(live),
+            );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        Hash,
+    )]
+    enum Thing {
+        Car,
+        Cart,
+        Road,
+    }
+
+    impl Kind for Thing {
+        const ALL: &'static [Self] =
+            &[Thing::Car, Thing::Cart, Thing::Road];
+
+        fn name(self) -> &'static str {
+            match self {
+                Thing::Car => "car",
+                Thing::Cart => "cart",
+                Thing::Road => "road",
+            }
+        }
+    }
+
+    #[test]
+    fn labels_read_back_as_the_same_id() {
+        let mut registry = Registry::new();
+        let car = registry.spawn(Thing::Car);
+        let cart = registry.spawn(Thing::Cart);
+        assert_eq!(
+            cart.label(),
+            "cart0"
+        );
+        assert_eq!(
+            registry.get("car0"),
+            Some(car)
+        );
+        assert_eq!(
+            registry.get("cart0"),
+            Some(cart)
+        );
+        assert_eq!(
+            Id::<Thing>::from_label("cart12").map(Id::kind),
+            Some(Thing::Cart)
+        );
+        for label in [
+            "car",
+            "car01",
+            "carX3",
+            "road-1",
+            "bus0",
+            "car99999999999",
+        ] {
+            assert_eq!(
+                Id::<Thing>::from_label(label),
+                None,
+                "{}",
+                label
+            );
+        }
+        // Not spawned here
+        assert_eq!(
+            registry.get("road0"),
+            None
+        );
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut registry = Registry::new();
+        let first = registry.spawn(Thing::Road);
+        assert!(registry.remove(first));
+        assert!(!registry.remove(first));
+        let second = registry.spawn(Thing::Road);
+        assert_ne!(
+            first,
+            second
+        );
+        registry.spawn(Thing::Car);
+        registry.clear();
+        assert!(registry.is_empty());
+        assert_eq!(
+            registry
+                .spawn(Thing::Road)
+                .number(),
+            2
+        );
+
+        let mut restored = Registry::new();
+        assert!(
+            restored
+                .adopt(Id::from_label("road7").unwrap())
+        );
+        restored.set_next(
+            Thing::Road,
+            3,
+        );
+        assert_eq!(
+            restored.next(Thing::Road),
+            8
+        );
+        assert_eq!(
+            restored.next(Thing::Car),
+            0
+        );
+        let last = Id::from_label(&format!("car{}", u32::MAX))
+            .unwrap();
+        assert!(!restored.adopt(last));
+        assert!(!restored.contains(last));
+        assert_eq!(
+            restored.next(Thing::Car),
+            0
+        );
+    }
+
+    #[test]
+    fn entities_go_by_kind() {
+        let mut registry = Registry::new();
+        let roads: Vec<_> = (0..3)
+            .map(|_| registry.spawn(Thing::Road))
+            .collect();
+        let cars: Vec<_> = (0..2)
+            .map(|_| registry.spawn(Thing::Car))
+            .collect();
+        registry.spawn(Thing::Cart);
+        assert_eq!(
+            registry
+                .of_kind(Thing::Road)
+                .collect::<Vec<_>>(),
+            roads
+        );
+        assert_eq!(
+            registry.remove_kind(Thing::Car),
+            cars
+        );
+        assert_eq!(
+            registry
+                .of_kind(Thing::Car)
+                .count(),
+            0
+        );
+        assert_eq!(
+            registry.len(),
+            4
+        );
+    }
+
+    #[derive(
+        Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+    )]
+    enum Bad {
+        Lane1,
+    }
+
+    impl Kind for Bad {
+        const ALL: &'static [Self] = &[Bad::Lane1];
+
+        fn name(self) -> &'static str {
+            "lane1"
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "lane1")]
+    fn names_ending_in_digits_are_refused() {
+        Registry::<Bad>::new();
+    }
+}
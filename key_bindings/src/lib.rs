@@ -0,0 +1,713 @@
+//! Action-to-keys maps for rusty_engine games, loaded from TOML.
+//!
+//! A key bindings file lists the keys for any of the actions, and the
+//! actions it leaves out keep their defaults:
+//!
+//! ```toml
+//! move_up = ["Up", "W"]
+//! move_down = ["Down", "S"]
+//! reset = ["G"]
+//! ```
+//!
+//! Key names are the names of rusty_engine's `KeyCode` variants.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use rusty_engine::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Something the player can do with a key.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Quit,
+    Reset,
+    /// Start a game from the title or game over screen.
+    Start,
+    Pause,
+}
+
+#[derive(Debug, Error)]
+pub enum BindingsError {
+    #[error("could not read key bindings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid key bindings: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("unknown key {0:?}")]
+    UnknownKey(String),
+    #[error(
+        "{key:?} is bound to both {first:?} and {second:?}"
+    )]
+    Conflict {
+        key: KeyCode,
+        first: Action,
+        second: Action,
+    },
+}
+
+/// The keys that trigger each [`Action`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    /// Arrow keys or WASD to move, `Q` or `Escape` to quit, `G` to
+    /// reset, `Space` or `Return` to start and `P` to pause.
+    fn default() -> Self {
+        Self {
+            bindings: BTreeMap::from([
+                (
+                    Action::MoveUp,
+                    vec![KeyCode::Up, KeyCode::W],
+                ),
+                (
+                    Action::MoveDown,
+                    vec![KeyCode::Down, KeyCode::S],
+                ),
+                (
+                    Action::MoveLeft,
+                    vec![KeyCode::Left, KeyCode::A],
+                ),
+                (
+                    Action::MoveRight,
+                    vec![KeyCode::Right, KeyCode::D],
+                ),
+                (
+                    Action::Quit,
+                    vec![KeyCode::Q, KeyCode::Escape],
+                ),
+                (
+                    Action::Reset,
+                    vec![KeyCode::G],
+                ),
+                (
+                    Action::Start,
+                    vec![KeyCode::Space, KeyCode::Return],
+                ),
+                (
+                    Action::Pause,
+                    vec![KeyCode::P],
+                ),
+            ]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Bindings with no keys for any action.
+    pub fn unbound() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// These bindings with `action` triggered by `keys` instead.
+    pub fn with(
+        mut self,
+        action: Action,
+        keys: Vec<KeyCode>,
+    ) -> Self {
+        self.bindings
+            .insert(
+                action, keys,
+            );
+        self
+    }
+
+    /// Load key bindings from a TOML file.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, BindingsError> {
+        Self::default().load_over(path)
+    }
+
+    /// Load a TOML file over these bindings. Actions it leaves out keep
+    /// their keys from `self`.
+    pub fn load_over<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<Self, BindingsError> {
+        self.merge_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Parse key bindings from TOML. Actions it leaves out keep their
+    /// default keys.
+    pub fn from_toml(
+        text: &str,
+    ) -> Result<Self, BindingsError> {
+        Self::default().merge_toml(text)
+    }
+
+    /// Parse TOML over these bindings. Actions it leaves out keep their
+    /// keys from `self`.
+    pub fn merge_toml(
+        self,
+        text: &str,
+    ) -> Result<Self, BindingsError> {
+        let names: BTreeMap<Action, Vec<String>> =
+            toml::from_str(text)?;
+        let mut bindings = self;
+        for (action, names) in names {
+            let keys = names
+                .iter()
+                .map(|name| parse_key(name))
+                .collect::<Result<_, _>>()?;
+            bindings
+                .bindings
+                .insert(
+                    action, keys,
+                );
+        }
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    /// Check that no key triggers more than one action.
+    fn validate(&self) -> Result<(), BindingsError> {
+        let mut owners: Vec<(
+            KeyCode,
+            Action,
+        )> = Vec::new();
+        for (&action, keys) in &self.bindings {
+            for &key in keys {
+                match owners
+                    .iter()
+                    .find(|(owned, _)| *owned == key)
+                {
+                    Some(&(_, first))
+                        if first != action =>
+                    {
+                        return Err(
+                            BindingsError::Conflict {
+                                key,
+                                first,
+                                second: action,
+                            },
+                        );
+                    }
+                    Some(_) => {}
+                    None => owners.push((
+                        key, action,
+                    )),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// A key that triggers one of `actions` in both these bindings and
+    /// `other`, as when two players would steer with the same key.
+    pub fn shared_key(
+        &self,
+        other: &KeyBindings,
+        actions: &[Action],
+    ) -> Option<KeyCode> {
+        actions
+            .iter()
+            .flat_map(|&action| self.keys(action))
+            .find(
+                |key| {
+                    actions
+                        .iter()
+                        .any(|&action| other.keys(action).contains(key))
+                },
+            )
+            .copied()
+    }
+
+    /// Whether any key bound to `action` is held down.
+    pub fn pressed(
+        &self,
+        keyboard_state: &KeyboardState,
+        action: Action,
+    ) -> bool {
+        keyboard_state.pressed_any(self.keys(action))
+    }
+
+    /// Whether any key bound to `action` was pressed this frame.
+    pub fn just_pressed(
+        &self,
+        keyboard_state: &KeyboardState,
+        action: Action,
+    ) -> bool {
+        keyboard_state.just_pressed_any(self.keys(action))
+    }
+}
+
+/// Keys that can be named in a key bindings file.
+const KEY_NAMES: &[(
+    &str,
+    KeyCode,
+)] = &[
+    (
+        "Key1",
+        KeyCode::Key1,
+    ),
+    (
+        "Key2",
+        KeyCode::Key2,
+    ),
+    (
+        "Key3",
+        KeyCode::Key3,
+    ),
+    (
+        "Key4",
+        KeyCode::Key4,
+    ),
+    (
+        "Key5",
+        KeyCode::Key5,
+    ),
+    (
+        "Key6",
+        KeyCode::Key6,
+    ),
+    (
+        "Key7",
+        KeyCode::Key7,
+    ),
+    (
+        "Key8",
+        KeyCode::Key8,
+    ),
+    (
+        "Key9",
+        KeyCode::Key9,
+    ),
+    (
+        "Key0",
+        KeyCode::Key0,
+    ),
+    (
+        "A",
+        KeyCode::A,
+    ),
+    (
+        "B",
+        KeyCode::B,
+    ),
+    (
+        "C",
+        KeyCode::C,
+    ),
+    (
+        "D",
+        KeyCode::D,
+    ),
+    (
+        "E",
+        KeyCode::E,
+    ),
+    (
+        "F",
+        KeyCode::F,
+    ),
+    (
+        "G",
+        KeyCode::G,
+    ),
+    (
+        "H",
+        KeyCode::H,
+    ),
+    (
+        "I",
+        KeyCode::I,
+    ),
+    (
+        "J",
+        KeyCode::J,
+    ),
+    (
+        "K",
+        KeyCode::K,
+    ),
+    (
+        "L",
+        KeyCode::L,
+    ),
+    (
+        "M",
+        KeyCode::M,
+    ),
+    (
+        "N",
+        KeyCode::N,
+    ),
+    (
+        "O",
+        KeyCode::O,
+    ),
+    (
+        "P",
+        KeyCode::P,
+    ),
+    (
+        "Q",
+        KeyCode::Q,
+    ),
+    (
+        "R",
+        KeyCode::R,
+    ),
+    (
+        "S",
+        KeyCode::S,
+    ),
+    (
+        "T",
+        KeyCode::T,
+    ),
+    (
+        "U",
+        KeyCode::U,
+    ),
+    (
+        "V",
+        KeyCode::V,
+    ),
+    (
+        "W",
+        KeyCode::W,
+    ),
+    (
+        "X",
+        KeyCode::X,
+    ),
+    (
+        "Y",
+        KeyCode::Y,
+    ),
+    (
+        "Z",
+        KeyCode::Z,
+    ),
+    (
+        "Escape",
+        KeyCode::Escape,
+    ),
+    (
+        "Up",
+        KeyCode::Up,
+    ),
+    (
+        "Down",
+        KeyCode::Down,
+    ),
+    (
+        "Left",
+        KeyCode::Left,
+    ),
+    (
+        "Right",
+        KeyCode::Right,
+    ),
+    (
+        "Space",
+        KeyCode::Space,
+    ),
+    (
+        "Return",
+        KeyCode::Return,
+    ),
+    (
+        "Back",
+        KeyCode::Back,
+    ),
+    (
+        "Tab",
+        KeyCode::Tab,
+    ),
+    (
+        "ShiftLeft",
+        KeyCode::ShiftLeft,
+    ),
+    (
+        "ShiftRight",
+        KeyCode::ShiftRight,
+    ),
+    (
+        "ControlLeft",
+        KeyCode::ControlLeft,
+    ),
+    (
+        "ControlRight",
+        KeyCode::ControlRight,
+    ),
+    // Older names for the modifier keys, still accepted in files.
+    (
+        "LShift",
+        KeyCode::ShiftLeft,
+    ),
+    (
+        "RShift",
+        KeyCode::ShiftRight,
+    ),
+    (
+        "LControl",
+        KeyCode::ControlLeft,
+    ),
+    (
+        "RControl",
+        KeyCode::ControlRight,
+    ),
+    (
+        "Numpad0",
+        KeyCode::Numpad0,
+    ),
+    (
+        "Numpad1",
+        KeyCode::Numpad1,
+    ),
+    (
+        "Numpad2",
+        KeyCode::Numpad2,
+    ),
+    (
+        "Numpad3",
+        KeyCode::Numpad3,
+    ),
+    (
+        "Numpad4",
+        KeyCode::Numpad4,
+    ),
+    (
+        "Numpad5",
+        KeyCode::Numpad5,
+    ),
+    (
+        "Numpad6",
+        KeyCode::Numpad6,
+    ),
+    (
+        "Numpad7",
+        KeyCode::Numpad7,
+    ),
+    (
+        "Numpad8",
+        KeyCode::Numpad8,
+    ),
+    (
+        "Numpad9",
+        KeyCode::Numpad9,
+    ),
+    (
+        "Comma",
+        KeyCode::Comma,
+    ),
+    (
+        "Period",
+        KeyCode::Period,
+    ),
+    (
+        "Slash",
+        KeyCode::Slash,
+    ),
+    (
+        "Semicolon",
+        KeyCode::Semicolon,
+    ),
+];
+
+/// Name of `key` in key bindings files, e.g. `"Space"` or `"A"`.
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|&&(_, named)| named == key)
+        .map(|&(name, _)| name)
+}
+
+fn parse_key(name: &str) -> Result<KeyCode, BindingsError> {
+    KEY_NAMES
+        .iter()
+        .find(
+            |(key_name, _)| {
+                key_name.eq_ignore_ascii_case(name)
+            },
+        )
+        .map(|&(_, key)| key)
+        .ok_or_else(|| {
+            BindingsError::UnknownKey(name.to_string())
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        KeyBindings::default()
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn file_overrides_only_listed_actions() {
+        let bindings = KeyBindings::from_toml(
+            r#"
+            move_down = ["Down", "R"]
+            move_right = ["right", "s"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            bindings.keys(Action::MoveDown),
+            [KeyCode::Down, KeyCode::R]
+        );
+        assert_eq!(
+            bindings.keys(Action::MoveRight),
+            [KeyCode::Right, KeyCode::S]
+        );
+        assert_eq!(
+            bindings.keys(Action::MoveUp),
+            [KeyCode::Up, KeyCode::W]
+        );
+    }
+
+    #[test]
+    fn merging_keeps_the_starting_bindings() {
+        let bindings = KeyBindings::unbound()
+            .with(
+                Action::MoveUp,
+                vec![KeyCode::Up],
+            )
+            .merge_toml(r#"move_down = ["Down"]"#)
+            .unwrap();
+        assert_eq!(
+            bindings.keys(Action::MoveUp),
+            [KeyCode::Up]
+        );
+        assert_eq!(
+            bindings.keys(Action::MoveDown),
+            [KeyCode::Down]
+        );
+        assert!(
+            bindings
+                .keys(Action::Quit)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn conflicting_keys_are_rejected() {
+        let error =
+            KeyBindings::from_toml(r#"reset = ["W"]"#)
+                .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                BindingsError::Conflict {
+                    key: KeyCode::W,
+                    first: Action::MoveUp,
+                    second: Action::Reset,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn shared_keys_are_found_across_actions() {
+        let steering = [
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveLeft,
+            Action::MoveRight,
+        ];
+        let arrows = KeyBindings::unbound().with(
+            Action::MoveUp,
+            vec![KeyCode::Up],
+        );
+        let wasd = KeyBindings::unbound()
+            .with(
+                Action::MoveUp,
+                vec![KeyCode::W],
+            )
+            .with(
+                Action::MoveDown,
+                vec![KeyCode::S],
+            );
+        assert_eq!(
+            arrows.shared_key(&wasd, &steering),
+            None
+        );
+        assert_eq!(
+            KeyBindings::default().shared_key(&wasd, &steering),
+            Some(KeyCode::W)
+        );
+        // Only the listed actions count
+        assert_eq!(
+            KeyBindings::default()
+                .shared_key(&wasd, &[Action::MoveLeft]),
+            None
+        );
+    }
+
+    #[test]
+    fn modifier_keys_accept_old_and_new_names() {
+        let bindings = KeyBindings::from_toml(
+            r#"
+            start = ["ShiftLeft", "RShift"]
+            pause = ["LControl", "controlright"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            bindings.keys(Action::Start),
+            [KeyCode::ShiftLeft, KeyCode::ShiftRight]
+        );
+        assert_eq!(
+            bindings.keys(Action::Pause),
+            [KeyCode::ControlLeft, KeyCode::ControlRight]
+        );
+        assert_eq!(
+            key_name(KeyCode::ShiftLeft),
+            Some("ShiftLeft")
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_actions_are_rejected() {
+        assert!(
+            matches!(
+                KeyBindings::from_toml(
+                    r#"quit = ["Hyper"]"#
+                ),
+                Err(BindingsError::UnknownKey(_))
+            )
+        );
+        assert!(
+            matches!(
+                KeyBindings::from_toml(
+                    r#"jump = ["Space"]"#
+                ),
+                Err(BindingsError::Toml(_))
+            )
+        );
+    }
+}
@@ -0,0 +1,567 @@
+//! Translated user-facing text for rusty_engine games, loaded from
+//! TOML.
+//!
+//! A locale file maps message keys to templates. Arguments are filled
+//! in by name, so each language can put them in its own order, and a
+//! message that depends on a number lists a template for each plural
+//! category of the language:
+//!
+//! ```toml
+//! score = "{player}Score: {score}"
+//! wins = "Player {player} wins!"
+//! lives = { one = "{count} life left", other = "{count} lives left" }
+//! ```
+//!
+//! Write `{{` and `}}` for literal braces.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::{Display, Write};
+
+use log::warn;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LocaleError {
+    #[error("invalid locale: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("{key:?} has no \"other\" form")]
+    MissingOther { key: String },
+}
+
+/// The plural categories of
+/// [CLDR](https://cldr.unicode.org/index/cldr-spec/plural-rules).
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Plural {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl Plural {
+    /// The category of `count` in `language`. Languages without a rule
+    /// of their own follow English.
+    pub fn of(language: &str, count: u32) -> Plural {
+        let (tens, ones) = (
+            count % 100,
+            count % 10,
+        );
+        let few = (2..=4).contains(&ones)
+            && !(12..=14).contains(&tens);
+        match language {
+            "ja" | "ko" | "zh" => Plural::Other,
+            "fr" | "pt" if count <= 1 => Plural::One,
+            "fr" | "pt" => Plural::Other,
+            "pl" if count == 1 => Plural::One,
+            "pl" if few => Plural::Few,
+            "pl" => Plural::Many,
+            "ru" | "uk" if ones == 1 && tens != 11 => {
+                Plural::One
+            }
+            "ru" | "uk" if few => Plural::Few,
+            "ru" | "uk" => Plural::Many,
+            _ if count == 1 => Plural::One,
+            _ => Plural::Other,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum Message {
+    Text(String),
+    Plural(BTreeMap<Plural, String>),
+}
+
+impl Message {
+    fn template(&self, plural: Plural) -> &str {
+        match self {
+            Message::Text(text) => text,
+            Message::Plural(forms) => forms
+                .get(&plural)
+                .or_else(|| forms.get(&Plural::Other))
+                .map(String::as_str)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The messages of one language, with another locale to fall back on
+/// for the ones it doesn't translate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Locale {
+    language: String,
+    messages: BTreeMap<String, Message>,
+    fallback: Option<Box<Locale>>,
+}
+
+impl Locale {
+    /// Parse the messages of `language` from TOML.
+    pub fn from_toml(
+        language: &str,
+        text: &str,
+    ) -> Result<Self, LocaleError> {
+        let messages: BTreeMap<String, Message> =
+            toml::from_str(text)?;
+        let no_other = |message: &Message| match message {
+            Message::Plural(forms) => {
+                !forms.contains_key(&Plural::Other)
+            }
+            Message::Text(_) => false,
+        };
+        if let Some((key, _)) = messages
+            .iter()
+            .find(|(_, message)| no_other(message))
+        {
+            return Err(
+                LocaleError::MissingOther {
+                    key: key.clone(),
+                },
+            );
+        }
+        Ok(
+            Self {
+                language: language.to_string(),
+                messages,
+                fallback: None,
+            },
+        )
+    }
+
+    /// This locale, looking up messages it lacks in `fallback`.
+    pub fn with_fallback(
+        mut self,
+        fallback: Locale,
+    ) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Keys of the messages this locale has itself, without those of
+    /// its fallback.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.messages
+            .keys()
+            .map(String::as_str)
+    }
+
+    /// The message for `key` without arguments.
+    pub fn text(&self, key: &str) -> String {
+        self.format(
+            key,
+            &[],
+        )
+    }
+
+    /// The message for `key` with `args` filled in. A plural message
+    /// uses its "other" form.
+    pub fn format(
+        &self,
+        key: &str,
+        args: &[(
+            &str,
+            &dyn Display,
+        )],
+    ) -> String {
+        self.render(
+            key, None, args,
+        )
+    }
+
+    /// The message for `key` in the plural form for `count`, which is
+    /// also passed as the `count` argument.
+    pub fn plural(
+        &self,
+        key: &str,
+        count: u32,
+        args: &[(
+            &str,
+            &dyn Display,
+        )],
+    ) -> String {
+        let mut args = args.to_vec();
+        args.push((
+            "count", &count,
+        ));
+        self.render(
+            key,
+            Some(count),
+            &args,
+        )
+    }
+
+    /// Looks `key` up here and then in the fallbacks. A key no locale
+    /// has is shown as is, so a missing translation is easy to spot.
+    fn render(
+        &self,
+        key: &str,
+        count: Option<u32>,
+        args: &[(
+            &str,
+            &dyn Display,
+        )],
+    ) -> String {
+        let mut locale = Some(self);
+        while let Some(current) = locale {
+            if let Some(message) = current
+                .messages
+                .get(key)
+            {
+                let plural = count.map_or(
+                    Plural::Other,
+                    |count| {
+                        Plural::of(
+                            &current.language,
+                            count,
+                        )
+                    },
+                );
+                return fill(
+                    message.template(plural),
+                    args,
+                );
+            }
+            locale = current
+                .fallback
+                .as_deref();
+        }
+        key.to_string()
+    }
+}
+
+/// `template` with each `{name}` replaced by the argument `name`.
+/// Placeholders without an argument are left in.
+fn fill(
+    template: &str,
+    args: &[(
+        &str,
+        &dyn Display,
+    )],
+) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}")
+        {
+            text.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(
+                |inner| {
+                    inner
+                        .find('}')
+                        .map(|end| &inner[..end])
+                },
+            );
+        match placeholder.and_then(
+            |name| {
+                args.iter()
+                    .find(|(arg, _)| *arg == name)
+            },
+        ) {
+            Some((name, value)) => {
+                let _ = write!(
+                    text,
+                    "{}",
+                    value
+                );
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                text.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// The language of a locale name such as `"de_DE.UTF-8"` or `"pt-BR"`.
+pub fn language(name: &str) -> String {
+    name.split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// The language asked for by `setting` if given, otherwise by the
+/// `LC_ALL`, `LC_MESSAGES` or `LANG` environment variables.
+pub fn requested(setting: Option<&str>) -> Option<String> {
+    setting
+        .map(str::to_string)
+        .or_else(|| {
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| env::var(name).ok())
+                .find(|value| !value.is_empty())
+        })
+        .map(|name| language(&name))
+        .filter(
+            |language| {
+                !language.is_empty()
+                    && language != "c"
+                    && language != "posix"
+            },
+        )
+}
+
+/// The locale for `requested` out of `builtin` pairs of language and
+/// TOML. The first of them is the fallback for messages the others
+/// lack, and for languages that aren't built in.
+pub fn select(
+    requested: Option<&str>,
+    builtin: &[(
+        &str,
+        &str,
+    )],
+) -> Locale {
+    let parse = |&(language, text): &(
+        &str,
+        &str,
+    )| match Locale::from_toml(
+        language, text,
+    ) {
+        Ok(locale) => Some(locale),
+        Err(e) => {
+            warn!(
+                "Built-in {} locale: {}",
+                language, e
+            );
+            None
+        }
+    };
+    let default = builtin
+        .first()
+        .and_then(parse)
+        .unwrap_or_default();
+    let Some(requested) = requested.map(language) else {
+        return default;
+    };
+    match builtin
+        .iter()
+        .skip(1)
+        .find(|(language, _)| *language == requested)
+    {
+        Some(locale) => match parse(locale) {
+            Some(locale) => locale.with_fallback(default),
+            None => default,
+        },
+        None => {
+            if requested != default.language {
+                warn!(
+                    "No {} translation, using {}",
+                    requested, default.language
+                );
+            }
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EN: &str = r#"
+        score = "{player}Score: {score}"
+        wins = "Player {player} wins!"
+        feris = { one = "{count} feris", other = "{count} ferises" }
+        braces = "{{score}} is {score}"
+    "#;
+    const PL: &str = r#"
+        wins = "Wygrywa gracz {player}!"
+
+        [feris]
+        one = "{count} kot"
+        few = "{count} koty"
+        many = "{count} kotów"
+        other = "{count} kota"
+    "#;
+
+    #[test]
+    fn arguments_go_where_the_language_puts_them() {
+        let locale = select(
+            Some("pl_PL.UTF-8"),
+            &[
+                (
+                    "en", EN,
+                ),
+                (
+                    "pl", PL,
+                ),
+            ],
+        );
+        assert_eq!(
+            locale.language(),
+            "pl"
+        );
+        assert_eq!(
+            locale.format(
+                "wins",
+                &[("player", &2)]
+            ),
+            "Wygrywa gracz 2!"
+        );
+        assert_eq!(
+            locale.format(
+                "score",
+                &[("player", &"P1 "), ("score", &7)]
+            ),
+            "P1 Score: 7"
+        );
+        assert_eq!(
+            locale.format(
+                "braces",
+                &[("score", &7)]
+            ),
+            "{score} is 7"
+        );
+        assert_eq!(
+            locale.format(
+                "score",
+                &[]
+            ),
+            "{player}Score: {score}"
+        );
+        assert_eq!(
+            locale.text("missing"),
+            "missing"
+        );
+    }
+
+    #[test]
+    fn plurals_follow_the_language_rules() {
+        let locale = select(
+            Some("pl"),
+            &[
+                (
+                    "en", EN,
+                ),
+                (
+                    "pl", PL,
+                ),
+            ],
+        );
+        let feris = |count| {
+            locale.plural(
+                "feris",
+                count,
+                &[],
+            )
+        };
+        assert_eq!(
+            feris(1),
+            "1 kot"
+        );
+        assert_eq!(
+            feris(3),
+            "3 koty"
+        );
+        assert_eq!(
+            feris(12),
+            "12 kotów"
+        );
+        assert_eq!(
+            feris(22),
+            "22 koty"
+        );
+        assert_eq!(
+            feris(25),
+            "25 kotów"
+        );
+
+        let english = select(
+            None,
+            &[
+                (
+                    "en", EN,
+                ),
+                (
+                    "pl", PL,
+                ),
+            ],
+        );
+        assert_eq!(
+            english.plural(
+                "feris",
+                1,
+                &[]
+            ),
+            "1 feris"
+        );
+        assert_eq!(
+            english.plural(
+                "feris",
+                0,
+                &[]
+            ),
+            "0 ferises"
+        );
+        assert_eq!(
+            Plural::of("fr", 0),
+            Plural::One
+        );
+        assert_eq!(
+            Plural::of("ru", 21),
+            Plural::One
+        );
+    }
+
+    #[test]
+    fn unknown_languages_and_bad_files_fall_back() {
+        assert_eq!(
+            select(
+                Some("xx"),
+                &[("en", EN)]
+            )
+            .language(),
+            "en"
+        );
+        assert_eq!(
+            language("de_DE.UTF-8"),
+            "de"
+        );
+        assert!(
+            matches!(
+                Locale::from_toml(
+                    "en",
+                    r#"feris = { one = "a feris" }"#
+                ),
+                Err(LocaleError::MissingOther { .. })
+            )
+        );
+        assert!(
+            matches!(
+                Locale::from_toml(
+                    "en",
+                    "score = 3"
+                ),
+                Err(LocaleError::Toml(_))
+            )
+        );
+    }
+}
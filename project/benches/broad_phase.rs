@@ -0,0 +1,93 @@
+//! Time finding the touching bodies among growing numbers of feris,
+//! checking every pair against checking pairs sharing a grid cell:
+//!
+//! ```sh
+//! cargo bench -p rusty_engine_followanong --bench broad_phase
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rand::prelude::*;
+use rusty_engine::prelude::*;
+
+// The game is a binary, so borrow the module straight from its source
+#[allow(dead_code, unused_imports)]
+#[path = "../src/spatial.rs"]
+mod spatial;
+
+use spatial::{BroadPhase, Circle};
+
+/// Feris counts to time.
+const COUNTS: [usize; 4] = [100, 500, 2_000, 5_000];
+/// How long to keep timing each broad phase at each count.
+const TIME: Duration = Duration::from_millis(500);
+
+/// `count` feris of the default size scattered over the window, like a
+/// stress run of clicking.
+fn feris(count: usize) -> Vec<Circle> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..count)
+        .map(|_| {
+            (
+                Vec2::new(
+                    rng.gen_range(-640.0..640.0),
+                    rng.gen_range(-360.0..360.0),
+                ),
+                40.0,
+            )
+        })
+        .collect()
+}
+
+/// Mean time of a frame's detection, and the pairs found.
+fn time(
+    broad_phase: &mut BroadPhase,
+    circles: &[Circle],
+) -> (
+    Duration,
+    usize,
+) {
+    let pairs = broad_phase
+        .overlapping(circles)
+        .len();
+    let start = Instant::now();
+    let mut frames = 0;
+    while start.elapsed() < TIME {
+        black_box(
+            broad_phase.overlapping(black_box(circles)),
+        );
+        frames += 1;
+    }
+    (
+        start.elapsed() / frames,
+        pairs,
+    )
+}
+
+fn main() {
+    for count in COUNTS {
+        let circles = feris(count);
+        let (all_pairs, expected) = time(
+            &mut BroadPhase::AllPairs,
+            &circles,
+        );
+        let (grid, pairs) = time(
+            &mut BroadPhase::grid(),
+            &circles,
+        );
+        assert_eq!(
+            pairs, expected,
+            "the grid missed pairs"
+        );
+        println!(
+            "{:>5} feris, {:>6} touching: \
+             all pairs {:>10.3?}, grid {:>10.3?} ({:.1}x)",
+            count,
+            pairs,
+            all_pairs,
+            grid,
+            all_pairs.as_secs_f64() / grid.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! Goals kept across sessions, unlocked by watching gameplay events.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use locale::Locale;
+use log::warn;
+use rusty_engine::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{GameEvent, Subscriber};
+use crate::simulation::Effect;
+
+/// Feris to collect within [`QUICK_PAWS_SECONDS`] for
+/// [`Achievement::QuickPaws`].
+const QUICK_PAWS_FERIS: usize = 10;
+const QUICK_PAWS_SECONDS: f32 = 5.0;
+/// Score to reach without pausing for [`Achievement::NonStop`].
+const NON_STOP_SCORE: u32 = 100;
+/// Feris to place by clicking for [`Achievement::FerisFactory`].
+const FERIS_FACTORY_CLICKS: u32 = 50;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Achievement {
+    QuickPaws,
+    NonStop,
+    FerisFactory,
+}
+
+impl Achievement {
+    pub fn name(self, locale: &Locale) -> String {
+        locale.text(
+            match self {
+                Achievement::QuickPaws => {
+                    "achievement_quick_paws"
+                }
+                Achievement::NonStop => {
+                    "achievement_non_stop"
+                }
+                Achievement::FerisFactory => {
+                    "achievement_feris_factory"
+                }
+            },
+        )
+    }
+}
+
+/// Everything about the achievements that is kept on disk.
+#[derive(
+    Clone, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub struct Progress {
+    pub unlocked: BTreeSet<Achievement>,
+    /// Feris placed by clicking, counted towards
+    /// [`Achievement::FerisFactory`].
+    pub clicked_feris: u32,
+}
+
+impl Progress {
+    /// Load progress saved by [`Progress::save`]. A missing file is no
+    /// progress yet, and a corrupt one is reported with a warning.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                match serde_json::from_str(&contents) {
+                    Ok(progress) => progress,
+                    Err(e) => {
+                        warn!(
+                            "Corrupt achievements {}: {}",
+                            path.display(),
+                            e
+                        );
+                        Self::default()
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Self::default()
+            }
+            Err(e) => {
+                warn!(
+                    "Could not read achievements {}: {}",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Save the progress to `path` as JSON, creating its parent
+    /// directories if needed.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            serde_json::to_string_pretty(self)?,
+        )
+    }
+}
+
+/// Unlocks achievements as the events come in, with a toast for each.
+pub struct Achievements {
+    pub progress: Progress,
+    /// Where the progress is persisted. `None` disables saving.
+    path: Option<PathBuf>,
+    /// When in the round each player collected their latest feris.
+    recent: BTreeMap<usize, VecDeque<f32>>,
+    /// The round in progress has been paused.
+    paused: bool,
+    /// Language of the toasts.
+    locale: Locale,
+    /// `progress` has changed since it was last saved.
+    dirty: bool,
+}
+
+impl Achievements {
+    pub fn new(
+        progress: Progress,
+        path: Option<PathBuf>,
+        locale: Locale,
+    ) -> Self {
+        Self {
+            progress,
+            path,
+            recent: BTreeMap::new(),
+            paused: false,
+            locale,
+            dirty: false,
+        }
+    }
+
+    fn save(&mut self) {
+        self.dirty = false;
+        if let Some(path) = &self.path {
+            if let Err(e) = self
+                .progress
+                .save(path)
+            {
+                warn!(
+                    "Could not save achievements to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    fn unlock(
+        &mut self,
+        achievement: Achievement,
+        effects: &mut Vec<Effect>,
+    ) {
+        if !self
+            .progress
+            .unlocked
+            .insert(achievement)
+        {
+            return;
+        }
+        self.save();
+        effects.push(
+            Effect::Toast(
+                self.locale
+                    .format(
+                        "achievement_unlocked",
+                        &[(
+                            "name",
+                            &achievement.name(&self.locale),
+                        )],
+                    ),
+            ),
+        );
+        effects.push(
+            Effect::PlaySfx(
+                SfxPreset::Confirmation2,
+                0.6,
+            ),
+        );
+    }
+}
+
+impl Subscriber for Achievements {
+    fn notify(
+        &mut self,
+        event: &GameEvent,
+        effects: &mut Vec<Effect>,
+    ) {
+        match *event {
+            GameEvent::RoundStarted => {
+                self.recent
+                    .clear();
+                self.paused = false;
+            }
+            GameEvent::Paused => self.paused = true,
+            GameEvent::FerisSpawned {
+                clicked: true,
+                ..
+            } => {
+                if self
+                    .progress
+                    .unlocked
+                    .contains(&Achievement::FerisFactory)
+                {
+                    return;
+                }
+                self.progress
+                    .clicked_feris += 1;
+                if self
+                    .progress
+                    .clicked_feris
+                    >= FERIS_FACTORY_CLICKS
+                {
+                    self.unlock(
+                        Achievement::FerisFactory,
+                        effects,
+                    );
+                } else {
+                    // Clicks come too fast to write each one
+                    self.dirty = true;
+                }
+            }
+            GameEvent::RoundEnded { .. }
+            | GameEvent::Reset
+            | GameEvent::Quit
+                if self.dirty =>
+            {
+                self.save();
+            }
+            GameEvent::FerisCollected {
+                player,
+                score,
+                seconds,
+                ..
+            } => {
+                let recent = self
+                    .recent
+                    .entry(player)
+                    .or_default();
+                recent.push_back(seconds);
+                while recent
+                    .front()
+                    .is_some_and(
+                        |&start| {
+                            seconds - start
+                                > QUICK_PAWS_SECONDS
+                        },
+                    )
+                {
+                    recent.pop_front();
+                }
+                if recent.len() >= QUICK_PAWS_FERIS {
+                    self.unlock(
+                        Achievement::QuickPaws,
+                        effects,
+                    );
+                }
+                if !self.paused && score >= NON_STOP_SCORE {
+                    self.unlock(
+                        Achievement::NonStop,
+                        effects,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::locales;
+
+    fn collected(seconds: f32, score: u32) -> GameEvent {
+        GameEvent::FerisCollected {
+            player: 0,
+            points: 1,
+            score,
+            seconds,
+            age: 1.0,
+        }
+    }
+
+    fn toasts(effects: &[Effect]) -> usize {
+        effects
+            .iter()
+            .filter(
+                |effect| {
+                    matches!(
+                        effect,
+                        Effect::Toast(_)
+                    )
+                },
+            )
+            .count()
+    }
+
+    #[test]
+    fn quick_paws_needs_ten_feris_in_five_seconds() {
+        let mut achievements = Achievements::new(
+            Progress::default(),
+            None,
+            locales::select(None),
+        );
+        let mut effects = Vec::new();
+        // Ten feris, but spread over more than five seconds
+        for i in 0..10 {
+            achievements.notify(
+                &collected(
+                    i as f32 * 0.6,
+                    i,
+                ),
+                &mut effects,
+            );
+        }
+        assert_eq!(
+            toasts(&effects),
+            0
+        );
+        achievements.notify(
+            &collected(
+                5.5, 10,
+            ),
+            &mut effects,
+        );
+        assert!(
+            matches!(
+                &effects[0],
+                Effect::Toast(message)
+                    if message == "Achievement unlocked: Quick Paws"
+            )
+        );
+        achievements.notify(
+            &collected(
+                5.6, 11,
+            ),
+            &mut effects,
+        );
+        assert_eq!(
+            toasts(&effects),
+            1
+        );
+    }
+
+    #[test]
+    fn pausing_rules_out_non_stop_for_the_round() {
+        let mut achievements = Achievements::new(
+            Progress::default(),
+            None,
+            locales::select(None),
+        );
+        let mut effects = Vec::new();
+        for event in [
+            GameEvent::RoundStarted,
+            GameEvent::Paused,
+            GameEvent::Resumed,
+            collected(
+                60.0, 120,
+            ),
+        ] {
+            achievements.notify(
+                &event,
+                &mut effects,
+            );
+        }
+        assert!(effects.is_empty());
+        for event in [
+            GameEvent::RoundStarted,
+            collected(
+                60.0, 100,
+            ),
+        ] {
+            achievements.notify(
+                &event,
+                &mut effects,
+            );
+        }
+        assert!(
+            achievements
+                .progress
+                .unlocked
+                .contains(&Achievement::NonStop)
+        );
+    }
+
+    #[test]
+    fn clicked_feris_count_across_sessions() {
+        let mut achievements = Achievements::new(
+            Progress {
+                unlocked: BTreeSet::new(),
+                clicked_feris: 48,
+            },
+            None,
+            locales::select(None),
+        );
+        let mut effects = Vec::new();
+        for clicked in [true, false, true] {
+            achievements.notify(
+                &GameEvent::FerisSpawned {
+                    clicked,
+                    position: Vec2::ZERO,
+                },
+                &mut effects,
+            );
+        }
+        assert_eq!(
+            toasts(&effects),
+            1
+        );
+        let json =
+            serde_json::to_string(&achievements.progress)
+                .unwrap();
+        let progress: Progress =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            progress.clicked_feris,
+            50
+        );
+        assert!(
+            progress
+                .unlocked
+                .contains(&Achievement::FerisFactory)
+        );
+    }
+
+    #[test]
+    fn clicks_are_saved_when_the_round_ends() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "achievements-test-{}.json",
+                std::process::id()
+            ),
+        );
+        let mut achievements = Achievements::new(
+            Progress::default(),
+            Some(path.clone()),
+            locales::select(None),
+        );
+        let mut effects = Vec::new();
+        for _ in 0..3 {
+            achievements.notify(
+                &GameEvent::FerisSpawned {
+                    clicked: true,
+                    position: Vec2::ZERO,
+                },
+                &mut effects,
+            );
+        }
+        assert!(!path.exists());
+        achievements.notify(
+            &GameEvent::RoundEnded { scores: vec![0] },
+            &mut effects,
+        );
+        assert_eq!(
+            Progress::load(&path).clicked_feris,
+            3
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use rusty_engine::prelude::*;
+
+use crate::simulation::World;
+use crate::spatial::BroadPhase;
+
+/// Pair with its labels in a stable order, so the same two bodies
+/// always produce the same pair.
+fn ordered_pair(a: &str, b: &str) -> CollisionPair {
+    if a <= b {
+        CollisionPair(
+            a.to_string(),
+            b.to_string(),
+        )
+    } else {
+        CollisionPair(
+            b.to_string(),
+            a.to_string(),
+        )
+    }
+}
+
+/// Find the bodies in `world` that touch, using `broad_phase` to
+/// narrow down the pairs to check.
+///
+/// `contacts` holds the pairs that were touching after the previous
+/// call. A pair produces a `Begin` event on the first frame it touches
+/// and an `End` event on the first frame it doesn't. Pairs whose bodies
+/// left the world are forgotten without an event.
+pub fn detect(
+    world: &World,
+    contacts: &mut HashSet<CollisionPair>,
+    broad_phase: &mut BroadPhase,
+) -> Vec<CollisionEvent> {
+    let (labels, circles): (
+        Vec<&str>,
+        Vec<_>,
+    ) = world
+        .bodies()
+        .map(
+            |(label, body)| {
+                (
+                    label,
+                    (
+                        body.translation,
+                        body.radius,
+                    ),
+                )
+            },
+        )
+        .unzip();
+    let mut events = Vec::new();
+    let touching: HashSet<CollisionPair> = broad_phase
+        .overlapping(&circles)
+        .into_iter()
+        .map(
+            |(a, b)| {
+                ordered_pair(
+                    labels[a], labels[b],
+                )
+            },
+        )
+        .collect();
+    for pair in &touching {
+        if !contacts.contains(pair) {
+            events.push(
+                CollisionEvent {
+                    state: CollisionState::Begin,
+                    pair: pair.clone(),
+                },
+            );
+        }
+    }
+    for pair in contacts.iter() {
+        if !touching.contains(pair)
+            && world.contains(&pair.0)
+            && world.contains(&pair.1)
+        {
+            events.push(
+                CollisionEvent {
+                    state: CollisionState::End,
+                    pair: pair.clone(),
+                },
+            );
+        }
+    }
+    // HashSet iteration order is random, sort for reproducible frames
+    events.sort_by(
+        |a, b| {
+            (
+                a.pair
+                    .array(),
+                a.state
+                    .is_end(),
+            )
+                .cmp(&(
+                    b.pair
+                        .array(),
+                    b.state
+                        .is_end(),
+                ))
+        },
+    );
+    *contacts = touching;
+    events
+}
@@ -0,0 +1,438 @@
+//! How the game gets harder as the score goes up.
+//!
+//! The defaults can be replaced by a TOML file listing the levels in
+//! order of their score thresholds:
+//!
+//! ```toml
+//! [[levels]]
+//! score = 0
+//! spawn_interval = 2.0
+//! feris_scale = 0.4
+//! feris_per_spawn = 1
+//! max_feris = 6
+//! behavior = { wander = 40.0 }
+//! ```
+//!
+//! A level without a `behavior` spawns feris that stand still, and one
+//! without `max_feris` keeps spawning however many are on the field.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::steering::Behavior;
+
+#[derive(Debug, Error)]
+pub enum DifficultyError {
+    #[error("could not read difficulty: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid difficulty: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("the first level must start at score 0")]
+    NoFirstLevel,
+    #[error("level {0} starts at a lower score than the level before it")]
+    Unordered(usize),
+    #[error("level {0} must spawn feris more than 0 seconds apart")]
+    SpawnInterval(usize),
+    #[error(
+        "level {0} must spawn feris bigger than nothing"
+    )]
+    FerisScale(usize),
+    #[error("level {0} spawns no feris")]
+    NoFeris(usize),
+    #[error(
+        "the behavior {1} of level {0} is out of range"
+    )]
+    Behavior(
+        usize,
+        &'static str,
+    ),
+}
+
+/// Settings that apply once the score reaches `score`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Level {
+    /// Score at which this level starts.
+    pub score: u32,
+    /// Seconds between feris spawns.
+    pub spawn_interval: f32,
+    /// Scale of newly spawned feris.
+    pub feris_scale: f32,
+    /// How many feris appear together at each spawn.
+    pub feris_per_spawn: u32,
+    /// Most feris on the field at once. Spawns hold off while there
+    /// are this many, though clicking still adds more.
+    #[serde(default)]
+    pub max_feris: Option<u32>,
+    /// How newly spawned feris move about.
+    #[serde(default)]
+    pub behavior: Behavior,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Difficulty {
+    levels: Vec<Level>,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            levels: vec![
+                Level {
+                    score: 0,
+                    spawn_interval: 2.0,
+                    feris_scale: 0.4,
+                    feris_per_spawn: 1,
+                    max_feris: Some(6),
+                    behavior: Behavior::default(),
+                },
+                Level {
+                    score: 10,
+                    spawn_interval: 1.6,
+                    feris_scale: 0.35,
+                    feris_per_spawn: 1,
+                    max_feris: Some(8),
+                    behavior: Behavior {
+                        wander: 40.0,
+                        ..Default::default()
+                    },
+                },
+                Level {
+                    score: 25,
+                    spawn_interval: 1.2,
+                    feris_scale: 0.3,
+                    feris_per_spawn: 2,
+                    max_feris: Some(10),
+                    behavior: Behavior {
+                        wander: 60.0,
+                        flee_radius: 120.0,
+                        flee_speed: 180.0,
+                        chase_radius: 0.0,
+                        chase_speed: 0.0,
+                        flock: 0.0,
+                    },
+                },
+                Level {
+                    score: 50,
+                    spawn_interval: 0.9,
+                    feris_scale: 0.25,
+                    feris_per_spawn: 2,
+                    max_feris: Some(12),
+                    behavior: Behavior {
+                        wander: 70.0,
+                        flee_radius: 150.0,
+                        flee_speed: 220.0,
+                        chase_radius: 0.0,
+                        chase_speed: 0.0,
+                        flock: 0.5,
+                    },
+                },
+                Level {
+                    score: 100,
+                    spawn_interval: 0.7,
+                    feris_scale: 0.2,
+                    feris_per_spawn: 3,
+                    max_feris: Some(15),
+                    behavior: Behavior {
+                        wander: 80.0,
+                        flee_radius: 180.0,
+                        flee_speed: 260.0,
+                        // Hang about the cars, just out of reach
+                        chase_radius: 400.0,
+                        chase_speed: 120.0,
+                        flock: 0.7,
+                    },
+                },
+            ],
+        }
+    }
+}
+
+impl Difficulty {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, DifficultyError> {
+        let difficulty: Self =
+            toml::from_str(&fs::read_to_string(path)?)?;
+        difficulty.validate()?;
+        Ok(difficulty)
+    }
+
+    fn validate(&self) -> Result<(), DifficultyError> {
+        if self
+            .levels
+            .first()
+            .map(|level| level.score)
+            != Some(0)
+        {
+            return Err(DifficultyError::NoFirstLevel);
+        }
+        for (i, pair) in self
+            .levels
+            .windows(2)
+            .enumerate()
+        {
+            if pair[1].score < pair[0].score {
+                return Err(
+                    DifficultyError::Unordered(i + 1),
+                );
+            }
+        }
+        for (i, level) in self
+            .levels
+            .iter()
+            .enumerate()
+        {
+            // Both become timer and sprite sizes, which can't be
+            // negative, endless or not a number
+            if !level
+                .spawn_interval
+                .is_finite()
+                || level.spawn_interval <= 0.0
+            {
+                return Err(
+                    DifficultyError::SpawnInterval(i),
+                );
+            }
+            if !level
+                .feris_scale
+                .is_finite()
+                || level.feris_scale <= 0.0
+            {
+                return Err(DifficultyError::FerisScale(i));
+            }
+            if level.feris_per_spawn == 0
+                || level.max_feris == Some(0)
+            {
+                return Err(DifficultyError::NoFeris(i));
+            }
+            let behavior = &level.behavior;
+            for (name, value) in [
+                (
+                    "wander",
+                    behavior.wander,
+                ),
+                (
+                    "flee_radius",
+                    behavior.flee_radius,
+                ),
+                (
+                    "flee_speed",
+                    behavior.flee_speed,
+                ),
+                (
+                    "chase_radius",
+                    behavior.chase_radius,
+                ),
+                (
+                    "chase_speed",
+                    behavior.chase_speed,
+                ),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    return Err(
+                        DifficultyError::Behavior(
+                            i, name,
+                        ),
+                    );
+                }
+            }
+            if !(0.0..=1.0).contains(&behavior.flock) {
+                return Err(
+                    DifficultyError::Behavior(
+                        i, "flock",
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Index of the last level whose threshold `score` has reached.
+    pub fn level_for(&self, score: u32) -> usize {
+        self.levels
+            .iter()
+            .rposition(|level| level.score <= score)
+            .unwrap_or(0)
+    }
+
+    pub fn level(&self, index: usize) -> &Level {
+        &self.levels[index]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn levels_follow_the_score() {
+        let difficulty = Difficulty::default();
+        assert_eq!(
+            difficulty.level_for(0),
+            0
+        );
+        assert_eq!(
+            difficulty.level_for(9),
+            0
+        );
+        assert_eq!(
+            difficulty.level_for(10),
+            1
+        );
+        assert_eq!(
+            difficulty.level_for(1_000),
+            4
+        );
+    }
+
+    #[test]
+    fn levels_must_start_at_zero_and_increase() {
+        let level = |score| Level {
+            score,
+            spawn_interval: 1.0,
+            feris_scale: 0.4,
+            feris_per_spawn: 1,
+            max_feris: None,
+            behavior: Behavior::default(),
+        };
+        let difficulty = Difficulty {
+            levels: vec![level(5)],
+        };
+        assert!(
+            matches!(
+                difficulty.validate(),
+                Err(DifficultyError::NoFirstLevel)
+            )
+        );
+        let difficulty = Difficulty {
+            levels: vec![level(0), level(20), level(10)],
+        };
+        assert!(
+            matches!(
+                difficulty.validate(),
+                Err(DifficultyError::Unordered(2))
+            )
+        );
+    }
+
+    #[test]
+    fn levels_must_spawn_feris() {
+        fn with(
+            change: impl FnOnce(&mut Level),
+        ) -> Result<(), DifficultyError> {
+            let mut difficulty = Difficulty::default();
+            change(&mut difficulty.levels[1]);
+            difficulty.validate()
+        }
+        assert!(with(|_| ()).is_ok());
+        for bad in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(
+                matches!(
+                    with(
+                        |level| level.spawn_interval = bad
+                    ),
+                    Err(DifficultyError::SpawnInterval(1))
+                ),
+                "{}",
+                bad
+            );
+            assert!(
+                matches!(
+                    with(|level| level.feris_scale = bad),
+                    Err(DifficultyError::FerisScale(1))
+                ),
+                "{}",
+                bad
+            );
+        }
+        assert!(
+            matches!(
+                with(|level| level.feris_per_spawn = 0),
+                Err(DifficultyError::NoFeris(1))
+            )
+        );
+        assert!(
+            matches!(
+                with(|level| level.max_feris = Some(0)),
+                Err(DifficultyError::NoFeris(1))
+            )
+        );
+    }
+
+    #[test]
+    fn behaviors_must_be_in_range() {
+        fn with(
+            change: impl FnOnce(&mut Behavior),
+        ) -> Result<(), DifficultyError> {
+            let mut difficulty = Difficulty::default();
+            change(&mut difficulty.levels[2].behavior);
+            difficulty.validate()
+        }
+        for bad in [-1.0, f32::NAN, f32::INFINITY] {
+            assert!(
+                matches!(
+                    with(|behavior| behavior.wander = bad),
+                    Err(
+                        DifficultyError::Behavior(
+                            2, "wander"
+                        )
+                    )
+                ),
+                "{}",
+                bad
+            );
+            assert!(
+                matches!(
+                    with(
+                        |behavior| behavior.flee_radius =
+                            bad
+                    ),
+                    Err(
+                        DifficultyError::Behavior(
+                            2,
+                            "flee_radius"
+                        )
+                    )
+                ),
+                "{}",
+                bad
+            );
+            assert!(
+                matches!(
+                    with(
+                        |behavior| behavior.chase_speed =
+                            bad
+                    ),
+                    Err(
+                        DifficultyError::Behavior(
+                            2,
+                            "chase_speed"
+                        )
+                    )
+                ),
+                "{}",
+                bad
+            );
+        }
+        for bad in [-0.5, 1.5, f32::NAN] {
+            assert!(
+                matches!(
+                    with(|behavior| behavior.flock = bad),
+                    Err(
+                        DifficultyError::Behavior(
+                            2, "flock"
+                        )
+                    )
+                ),
+                "{}",
+                bad
+            );
+        }
+        assert!(
+            with(|behavior| behavior.flock = 1.0).is_ok()
+        );
+    }
+}
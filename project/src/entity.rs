@@ -0,0 +1,79 @@
+//! What each sprite on the field is.
+//!
+//! Feris and power-ups are spawned through an
+//! [`entities::Registry`], which labels their sprites so the kind can
+//! always be read back. The cars have fixed labels of their own, in
+//! [`PLAYER_LABELS`](crate::simulation::PLAYER_LABELS).
+
+use entities::{Id, Kind};
+
+use crate::powerup::PowerUp;
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Entity {
+    Feris,
+    PowerUp(PowerUp),
+}
+
+/// A feris or power-up on the field.
+pub type EntityId = Id<Entity>;
+
+impl Kind for Entity {
+    const ALL: &'static [Self] = &[
+        Entity::Feris,
+        Entity::PowerUp(PowerUp::Magnet),
+        Entity::PowerUp(PowerUp::Speed),
+        Entity::PowerUp(PowerUp::Doubler),
+        Entity::PowerUp(PowerUp::Shrink),
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Entity::Feris => "feris",
+            Entity::PowerUp(power_up) => power_up.label(),
+        }
+    }
+}
+
+/// Kind of the sprite labelled `label`, if it is a feris or power-up.
+pub fn kind_of(label: &str) -> Option<Entity> {
+    EntityId::from_label(label).map(Id::kind)
+}
+
+#[cfg(test)]
+mod test {
+    use entities::Registry;
+
+    use super::*;
+
+    #[test]
+    fn labels_name_their_kind() {
+        for power_up in PowerUp::ALL {
+            assert_eq!(
+                kind_of(
+                    &format!(
+                        "{}12",
+                        power_up.label()
+                    )
+                ),
+                Some(Entity::PowerUp(power_up))
+            );
+        }
+        assert_eq!(
+            kind_of("feris3"),
+            Some(Entity::Feris)
+        );
+        assert_eq!(
+            kind_of("speed"),
+            None
+        );
+        assert_eq!(
+            kind_of("player1"),
+            None
+        );
+        // Every kind's name is fit to label sprites with
+        Registry::<Entity>::new();
+    }
+}
@@ -0,0 +1,112 @@
+//! What happened during a frame, for whoever wants to know.
+//!
+//! [`simulation::step`](crate::simulation::step) records the
+//! [`GameEvent`]s of each frame in `GameState::events`, and
+//! `game_logic` hands them to the [`EventBus`], whose subscribers
+//! answer with effects of their own.
+
+use rusty_engine::prelude::*;
+
+use crate::powerup::PowerUp;
+use crate::simulation::Effect;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A new round began, from the title or game over screen or by
+    /// resetting.
+    RoundStarted,
+    /// The round in progress was thrown away and started over.
+    Reset,
+    Paused,
+    Resumed,
+    /// A feris appeared at `position`, placed by a click or by the
+    /// spawn timer.
+    FerisSpawned {
+        clicked: bool,
+        position: Vec2,
+    },
+    /// Player `player` collected a feris `seconds` into the round and
+    /// `age` seconds after it appeared, scoring `points` and bringing
+    /// their score to `score`.
+    FerisCollected {
+        player: usize,
+        points: u32,
+        score: u32,
+        seconds: f32,
+        age: f32,
+    },
+    /// The high score went up to `score`.
+    HighScore {
+        score: u32,
+    },
+    /// A feris expired uncollected.
+    FerisMissed,
+    /// Player `player`'s streak ran out.
+    StreakEnded {
+        player: usize,
+    },
+    PowerUpCollected {
+        player: usize,
+        power_up: PowerUp,
+    },
+    /// Time ran out, with each player's final score.
+    RoundEnded {
+        scores: Vec<u32>,
+    },
+    /// The game is about to exit.
+    Quit,
+}
+
+/// Something that reacts to gameplay events.
+pub trait Subscriber: Send + Sync {
+    /// Called at the start of every frame, before its events, with
+    /// the frame's length in seconds.
+    fn frame(&mut self, _delta: f32) {}
+
+    /// Handle `event`, pushing anything the engine has to do as a
+    /// result onto `effects`.
+    fn notify(
+        &mut self,
+        event: &GameEvent,
+        effects: &mut Vec<Effect>,
+    );
+}
+
+/// Passes every event on to each subscriber, in the order they
+/// subscribed.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn subscribe<S: Subscriber + 'static>(
+        &mut self,
+        subscriber: S,
+    ) {
+        self.subscribers
+            .push(Box::new(subscriber));
+    }
+
+    /// Notify every subscriber of a frame `delta` seconds long and
+    /// its `events`, returning the effects they ask for.
+    pub fn publish(
+        &mut self,
+        delta: f32,
+        events: &[GameEvent],
+    ) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        for subscriber in &mut self.subscribers {
+            subscriber.frame(delta);
+        }
+        for event in events {
+            for subscriber in &mut self.subscribers {
+                subscriber.notify(
+                    event,
+                    &mut effects,
+                );
+            }
+        }
+        effects
+    }
+}
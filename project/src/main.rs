@@ -1,44 +1,487 @@
+use std::path::Path;
+
+use key_bindings::{key_name, Action, KeyBindings};
+use locale::Locale;
+use log::{error, info, warn};
 use rand::prelude::*;
 use rusty_engine::prelude::*;
 
+mod achievements;
+mod collision;
+mod difficulty;
+mod entity;
+mod events;
+mod locales;
+mod physics;
+mod powerup;
+mod replay;
+mod save;
 mod score;
-use score::Score;
+mod screen;
+mod simulation;
+mod sink;
+mod spatial;
+mod steering;
+mod storage;
+use achievements::{Achievements, Progress};
+use difficulty::Difficulty;
+use entity::Entity;
+use physics::Physics;
+use replay::{Playback, Recorder, Recording};
+use save::{SaveError, SaveGame};
+use simulation::{
+    Body, Effect, GameState, Input, Player, Steering,
+    World, MAX_PLAYERS, PLAYER_LABELS, POWERUP_LABELS,
+    SCORE_LABELS,
+};
+use sink::TelemetrySink;
+use spatial::BroadPhase;
+
+/// Environment variable holding the spawn seed, used when `--seed` is
+/// not given on the command line.
+const SEED_VAR: &str = "FERIS_SEED";
+/// Files in the user config directory holding each player's key
+/// bindings.
+const KEY_BINDINGS_FILES: [&str; MAX_PLAYERS] =
+    ["key_bindings.toml", "key_bindings_player2.toml"];
+/// Keys typing initials for the leaderboard.
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+];
+/// Car of each player.
+const CARS: [SpritePreset; MAX_PLAYERS] = [
+    SpritePreset::RacingCarBlue,
+    SpritePreset::RacingCarRed,
+];
+/// File in the user config directory holding the difficulty levels.
+const DIFFICULTY_FILE: &str = "difficulty.toml";
+/// File in the user config directory holding the car's physics.
+const PHYSICS_FILE: &str = "physics.toml";
+/// File in the user data directory holding achievement progress.
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+/// How long a toast stays on the HUD.
+const TOAST_SECONDS: f32 = 3.0;
 
-#[derive(Resource)]
-// #[allow(dead_code)]
-struct GameState {
-    high_score: Score,
-    score: Score,
-    velocity: Vec2,
-    feris_index: u32,
-    spawn_timer: Timer,
+/// Value following `name` on the command line, e.g. `--seed 42`.
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
 }
 
-impl Default for GameState {
-    fn default() -> Self {
-        Self {
-            high_score: Score::new(
-                0, "High ",
-            ),
-            score: Score::default(),
-            velocity: Vec2::new(
-                1.0, 0.0,
+fn has_flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}
+
+/// Translation for `--lang <language>`, or for the language of the
+/// environment, e.g. `LANG=de_DE.UTF-8`.
+fn locale() -> Locale {
+    locales::select(
+        locale::requested(arg_value("--lang").as_deref())
+            .as_deref(),
+    )
+}
+
+/// Seed for feris spawning from `--seed <N>` or `FERIS_SEED`, or a
+/// random one so every run differs.
+fn seed() -> u64 {
+    let requested = arg_value("--seed")
+        .or_else(|| std::env::var(SEED_VAR).ok());
+    match requested {
+        Some(text) => match text.parse() {
+            Ok(seed) => seed,
+            Err(e) => {
+                warn!(
+                    "Ignoring invalid seed {:?}: {}",
+                    text, e
+                );
+                thread_rng().gen()
+            }
+        },
+        None => thread_rng().gen(),
+    }
+}
+
+/// Number of players from `--players <N>`, or one.
+fn players() -> usize {
+    let Some(text) = arg_value("--players") else {
+        return 1;
+    };
+    match text.parse() {
+        Ok(players)
+            if (1..=MAX_PLAYERS).contains(&players) =>
+        {
+            players
+        }
+        _ => {
+            warn!(
+                "Ignoring --players {:?}, there can be 1 to {}",
+                text, MAX_PLAYERS
+            );
+            1
+        }
+    }
+}
+
+/// Broad phase from `--broad-phase <all|grid>`, checking all pairs of
+/// bodies by default.
+fn broad_phase() -> BroadPhase {
+    match arg_value("--broad-phase").as_deref() {
+        None | Some("all") => BroadPhase::AllPairs,
+        Some("grid") => BroadPhase::grid(),
+        Some(other) => {
+            warn!(
+                "Ignoring --broad-phase {:?}, it can be all or grid",
+                other
+            );
+            BroadPhase::AllPairs
+        }
+    }
+}
+
+/// Key bindings for each of `players` from the user config directory.
+///
+/// On your own both the arrow keys and WASD steer. With two players
+/// the first steers with WASD and the second with the arrow keys. If
+/// the files would have two players steer with the same key, everyone
+/// gets the defaults instead.
+fn key_bindings(players: usize) -> Vec<KeyBindings> {
+    let defaults = |player: usize| match (
+        players, player,
+    ) {
+        (1, _) => KeyBindings::default(),
+        (_, 0) => KeyBindings::default()
+            .with(
+                Action::MoveUp,
+                vec![KeyCode::W],
+            )
+            .with(
+                Action::MoveDown,
+                vec![KeyCode::S],
+            )
+            .with(
+                Action::MoveLeft,
+                vec![KeyCode::A],
+            )
+            .with(
+                Action::MoveRight,
+                vec![KeyCode::D],
             ),
-            feris_index: 0,
-            spawn_timer: Timer::from_seconds(
-                2.0,
-                TimerMode::Repeating,
+        _ => KeyBindings::unbound()
+            .with(
+                Action::MoveUp,
+                vec![KeyCode::Up],
+            )
+            .with(
+                Action::MoveDown,
+                vec![KeyCode::Down],
+            )
+            .with(
+                Action::MoveLeft,
+                vec![KeyCode::Left],
+            )
+            .with(
+                Action::MoveRight,
+                vec![KeyCode::Right],
             ),
+    };
+    let loaded: Vec<_> = KEY_BINDINGS_FILES[..players]
+        .iter()
+        .enumerate()
+        .map(
+            |(player, file_name)| {
+                storage::load_config_or(
+                    file_name,
+                    || defaults(player),
+                    |path| defaults(player).load_over(path),
+                )
+            },
+        )
+        .collect();
+    let steering = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+    ];
+    for (first, bindings) in loaded
+        .iter()
+        .enumerate()
+    {
+        for (second, other) in loaded
+            .iter()
+            .enumerate()
+            .skip(first + 1)
+        {
+            if let Some(key) =
+                bindings.shared_key(other, &steering)
+            {
+                warn!(
+                    "Players {} and {} both steer with {:?}, using default key bindings",
+                    first + 1,
+                    second + 1,
+                    key
+                );
+                return (0..players)
+                    .map(defaults)
+                    .collect();
+            }
+        }
+    }
+    loaded
+}
+
+/// Apply the difficulty and physics settings from the user config
+/// directory, and the broad phase from the command line.
+fn configure(game_state: &mut GameState) {
+    game_state.broad_phase = broad_phase();
+    game_state.set_difficulty(
+        storage::load_config(
+            DIFFICULTY_FILE,
+            |path| Difficulty::load(path),
+        ),
+    );
+    game_state.physics = storage::load_config(
+        PHYSICS_FILE,
+        |path| Physics::load(path),
+    );
+}
+
+/// The round saved when the game was last quit partway, if any. The
+/// save is removed once read, so a round is only resumed once.
+fn saved_game(path: &Path) -> Option<SaveGame> {
+    match SaveGame::load(path) {
+        Ok(save) => {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!(
+                    "Could not remove save {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+            Some(save)
+        }
+        Err(SaveError::Io(e))
+            if e.kind() == std::io::ErrorKind::NotFound =>
+        {
+            None
+        }
+        Err(e) => {
+            error!(
+                "Could not resume the game saved in {}: {}",
+                path.display(),
+                e
+            );
+            None
         }
     }
 }
 
 fn main() {
+    env_logger::Builder::from_env(
+        env_logger::Env::default()
+            .default_filter_or("warn"),
+    )
+    .init();
+    let recording = arg_value("--replay").map(
+        |path| match Recording::load(&path) {
+            Ok(recording) => recording,
+            Err(e) => {
+                error!(
+                    "Could not load replay {}: {}",
+                    path, e
+                );
+                std::process::exit(1);
+            }
+        },
+    );
+    if has_flag("--headless") {
+        let Some(recording) = recording else {
+            error!("--headless needs --replay <file>");
+            std::process::exit(1);
+        };
+        let mut game_state =
+            GameState::with_seed(recording.seed);
+        game_state.locale = locale();
+        game_state.set_players(vec![
+            KeyBindings::default();
+            recording.players
+        ]);
+        configure(&mut game_state);
+        let (game_state, _) = replay::run(
+            &recording, game_state,
+        );
+        for player in &game_state.players {
+            println!(
+                "{}",
+                player
+                    .score
+                    .display(&game_state.locale)
+            );
+        }
+        return;
+    }
+
+    let (seed, players) = match &recording {
+        Some(recording) => (
+            recording.seed,
+            recording.players,
+        ),
+        None => (
+            seed(),
+            players(),
+        ),
+    };
+    info!(
+        "Spawning feris with seed {}",
+        seed
+    );
+    let mut game_state = GameState::new(seed);
+    game_state.locale = locale();
+    let saved = match (
+        &recording,
+        &game_state.save_path,
+    ) {
+        (None, Some(path)) => saved_game(path),
+        _ => None,
+    };
+    let players = saved
+        .as_ref()
+        .map_or(
+            players,
+            |save| {
+                save.players
+                    .len()
+            },
+        );
+    game_state.set_players(key_bindings(players));
+    configure(&mut game_state);
+    let (world, resumed) = match saved
+        .map(|save| save.restore(&mut game_state))
+    {
+        Some(Ok(world)) => {
+            let effects = simulation::resume(
+                &mut game_state,
+                &world,
+            );
+            (
+                world, effects,
+            )
+        }
+        Some(Err(e)) => {
+            error!(
+                "Could not resume the saved game: {}",
+                e
+            );
+            (
+                World::new(players),
+                Vec::new(),
+            )
+        }
+        None => (
+            World::new(players),
+            Vec::new(),
+        ),
+    };
+    if let Some(recording) = recording {
+        // Replays don't count towards the saved high score, the
+        // leaderboard or the achievements, and aren't saved to resume
+        // later. They play out against the leaderboard they were
+        // recorded with.
+        game_state.high_score_path = None;
+        game_state.leaderboard_path = None;
+        game_state.save_path = None;
+        game_state.leaderboard = recording
+            .leaderboard
+            .clone();
+        game_state.playback =
+            Some(Playback::new(recording));
+    } else {
+        let path = storage::data_file(ACHIEVEMENTS_FILE);
+        let progress = path
+            .as_ref()
+            .map(Progress::load)
+            .unwrap_or_default();
+        game_state
+            .bus
+            .subscribe(
+                Achievements::new(
+                    progress,
+                    path,
+                    game_state
+                        .locale
+                        .clone(),
+                ),
+            );
+    }
+    if let Some(path) = arg_value("--telemetry") {
+        match TelemetrySink::create(&path) {
+            Ok(sink) => game_state
+                .bus
+                .subscribe(sink),
+            Err(e) => warn!(
+                "Could not write telemetry to {}: {}",
+                path, e
+            ),
+        }
+    }
+    if let Some(path) = arg_value("--record") {
+        if !resumed.is_empty() {
+            warn!(
+                "Not recording to {}, a resumed round can't be replayed",
+                path
+            );
+        } else {
+            match Recorder::create(
+                &path,
+                seed,
+                players,
+                &game_state.leaderboard,
+            ) {
+                Ok(recorder) => {
+                    game_state.recorder = Some(recorder)
+                }
+                Err(e) => warn!(
+                    "Could not record to {}: {}",
+                    path, e
+                ),
+            }
+        }
+    }
     let mut game = Game::new();
 
     game.window_settings(
         Window {
-            title: "Tutorial".to_string(),
+            title: game_state
+                .locale
+                .text("window_title"),
             mode: WindowMode::Windowed,
             // resolution: WindowResolution::new(3440.0,
             // 1440.0), width: 2400,
@@ -47,298 +490,561 @@ fn main() {
         },
     );
 
-    let player = game.add_sprite(
-        "player",
-        SpritePreset::RacingCarBlue,
-    );
-    player.translation = Vec2::new(
-        0.0, 0.0,
-    );
-    // player.rotation = std::f32::consts::PI / 3.0;
-    player.scale = 1.3;
-    player.collision = true;
+    for (i, player) in game_state
+        .players
+        .iter()
+        .enumerate()
+    {
+        let car = game.add_sprite(
+            PLAYER_LABELS[i],
+            CARS[i],
+        );
+        car.translation = world.players[i].translation;
+        car.rotation = world.players[i].rotation;
+        car.scale = world.players[i].scale;
 
-    let score = game.add_text(
-        "score", "Score: 0",
-    );
-    score.translation = Vec2::new(
-        520.0, 320.0,
-    );
+        let score = game.add_text(
+            SCORE_LABELS[i],
+            format!(
+                "{:#}",
+                player
+                    .score
+                    .display(&game_state.locale)
+            ),
+        );
+        score.translation = Vec2::new(
+            420.0,
+            320.0 - 40.0 * i as f32,
+        );
+        let powerups = game.add_text(
+            POWERUP_LABELS[i],
+            "",
+        );
+        powerups.font_size = 20.0;
+    }
     let high_score = game.add_text(
         "high_score",
-        "High Score: 0",
+        game_state
+            .high_score
+            .display(&game_state.locale)
+            .to_string(),
     );
     high_score.translation = Vec2::new(
         -520.0, 320.0,
     );
-
-    game.audio_manager
-        .play_music(
-            MusicPreset::Classy8Bit,
-            0.1,
+    let seed_text = game.add_text(
+        "seed",
+        game_state
+            .locale
+            .format(
+                "seed",
+                &[(
+                    "seed",
+                    &game_state.seed,
+                )],
+            ),
+    );
+    seed_text.font_size = 20.0;
+    let banner = game.add_text(
+        "banner", "",
+    );
+    banner.translation = Vec2::new(
+        0.0, 150.0,
+    );
+    banner.font_size = 96.0;
+    let clock = game.add_text(
+        "clock", "",
+    );
+    clock.translation = Vec2::new(
+        0.0, 320.0,
+    );
+    let message = game.add_text(
+        "message",
+        game_state.message(),
+    );
+    message.font_size = 72.0;
+    let hint = game.add_text(
+        "hint",
+        game_state.hint(),
+    );
+    hint.translation = Vec2::new(
+        0.0, -80.0,
+    );
+    hint.font_size = 32.0;
+    let leaderboard = game.add_text(
+        "leaderboard",
+        game_state
+            .leaderboard
+            .text(&game_state.locale),
+    );
+    leaderboard.translation = Vec2::new(
+        0.0, -220.0,
+    );
+    leaderboard.font_size = 24.0;
+    let toast = game.add_text(
+        "toast", "",
+    );
+    toast.font_size = 32.0;
+    for effect in resumed {
+        apply_effect(
+            &mut game,
+            &mut game_state,
+            &world,
+            effect,
         );
+    }
+
+    if let Some((preset, volume)) = game_state
+        .screen
+        .music()
+    {
+        game.audio_manager
+            .play_music(
+                preset, volume,
+            );
+    }
     game.add_logic(game_logic);
 
-    game.run(GameState::default());
+    game.run(game_state);
 }
 
 fn game_logic(
     engine: &mut Engine,
     game_state: &mut GameState,
 ) {
-    if engine
-        .keyboard_state
-        .just_pressed(KeyCode::Q)
+    let score_y_offset = ((engine.time_since_startup_f64
+        * 7.0)
+        .sin()
+        * 10.0) as f32;
+    for (i, label) in SCORE_LABELS[..game_state
+        .players
+        .len()]
+        .iter()
+        .enumerate()
+    {
+        let score = engine
+            .texts
+            .get_mut(*label)
+            .unwrap();
+        score
+            .translation
+            .x = engine
+            .window_dimensions
+            .x
+            / 2.0
+            - 220.0;
+        score
+            .translation
+            .y = engine
+            .window_dimensions
+            .y
+            / 2.0
+            - 30.0
+            - 40.0 * i as f32
+            + score_y_offset;
+    }
+    let players = game_state
+        .players
+        .len();
+    for (i, label) in POWERUP_LABELS[..players]
+        .iter()
+        .enumerate()
     {
-        engine.should_exit = true;
+        let powerups = engine
+            .texts
+            .get_mut(*label)
+            .unwrap();
+        powerups.translation = Vec2::new(
+            engine
+                .window_dimensions
+                .x
+                / 2.0
+                - 220.0,
+            engine
+                .window_dimensions
+                .y
+                / 2.0
+                - 30.0
+                - 40.0 * players as f32
+                - 25.0 * i as f32,
+        );
     }
-
-    let score = engine
+    let high_score = engine
         .texts
-        .get_mut("score")
+        .get_mut("high_score")
         .unwrap();
-    score
+    high_score
         .translation
-        .x = engine
+        .x = -engine
         .window_dimensions
         .x
         / 2.0
-        - 80.0;
-    let score_y_offset = ((engine.time_since_startup_f64
-        * 7.0)
-        .sin()
-        * 10.0) as f32;
-    score
+        + 100.0;
+    high_score
         .translation
         .y = engine
         .window_dimensions
         .y
         / 2.0
-        - 30.0
-        + score_y_offset;
-    let high_score = engine
+        - 30.0;
+    let seed_text = engine
         .texts
-        .get_mut("high_score")
+        .get_mut("seed")
         .unwrap();
-    high_score
+    seed_text
         .translation
         .x = -engine
         .window_dimensions
         .x
         / 2.0
         + 100.0;
-    high_score
+    seed_text
+        .translation
+        .y = -engine
+        .window_dimensions
+        .y
+        / 2.0
+        + 20.0;
+    engine
+        .texts
+        .get_mut("clock")
+        .unwrap()
         .translation
         .y = engine
         .window_dimensions
         .y
         / 2.0
         - 30.0;
+    engine
+        .texts
+        .get_mut("toast")
+        .unwrap()
+        .translation
+        .y = engine
+        .window_dimensions
+        .y
+        / 2.0
+        - 80.0;
 
-    for event in engine
-        .collision_events
-        .drain(..)
+    let input = match &mut game_state.playback {
+        Some(playback) => playback.next_input(),
+        None => read_input(
+            engine,
+            &game_state.players,
+            game_state
+                .name_entry
+                .is_some(),
+        ),
+    };
+    if let Some(Err(e)) = game_state
+        .recorder
+        .as_mut()
+        .map(|recorder| recorder.record(&input))
     {
-        #[allow(clippy::single_match)]
-        match event.state {
-            CollisionState::Begin => {
-                if event
-                    .pair
-                    .one_starts_with("player")
-                {
-                    for label in event.pair {
-                        if label != "player" {
-                            engine
-                                .sprites
-                                .remove(&label);
-                        }
-                    }
-                    game_state.score += 1;
-                    let score = engine
-                        .texts
-                        .get_mut("score")
-                        .unwrap();
-                    score.value = game_state
-                        .score
-                        .to_string();
-
-                    if game_state.score
-                        > game_state.high_score
-                    {
-                        game_state
-                            .high_score
-                            .value = game_state
-                            .score
-                            .value;
-                        let high_score = engine
-                            .texts
-                            .get_mut("high_score")
-                            .unwrap();
-                        high_score.value = game_state
-                            .high_score
-                            .to_string();
-                    }
-                    engine
-                        .audio_manager
-                        .play_sfx(
-                            SfxPreset::Jingle1,
-                            0.5,
-                        );
-                }
-            }
-            _ => {}
-        };
+        warn!(
+            "Stopped recording: {}",
+            e
+        );
+        game_state.recorder = None;
     }
-
-    // handle movement
-    const MAXIMUM_ACCELERATION: f32 = 1.0;
-    let mut acceleration = Vec2::new(
-        0.0, 0.0,
+    let mut world = read_world(
+        engine, game_state,
     );
-    if engine
-        .keyboard_state
-        .pressed_any(&[KeyCode::Up, KeyCode::W])
-    {
-        acceleration.y += MAXIMUM_ACCELERATION;
-    }
-    if engine
-        .keyboard_state
-        .pressed_any(&[KeyCode::Down, KeyCode::R])
-    {
-        acceleration.y -= MAXIMUM_ACCELERATION;
+    let mut effects = simulation::step(
+        game_state, &input, &mut world,
+    );
+    effects.extend(
+        game_state
+            .bus
+            .publish(
+                input.delta,
+                &game_state.events,
+            ),
+    );
+    if let Some(toast_timer) = &mut game_state.toast_timer {
+        if toast_timer
+            .tick(engine.delta)
+            .finished()
+        {
+            game_state.toast_timer = None;
+            effects.push(
+                Effect::SetText {
+                    label: "toast",
+                    value: String::new(),
+                },
+            );
+        }
     }
-    if engine
-        .keyboard_state
-        .pressed_any(&[KeyCode::Left, KeyCode::A])
+
+    for (label, body) in PLAYER_LABELS
+        .iter()
+        .zip(&world.players)
     {
-        acceleration.x -= MAXIMUM_ACCELERATION;
+        let car = engine
+            .sprites
+            .get_mut(*label)
+            .unwrap();
+        car.translation = body.translation;
+        car.rotation = body.rotation;
     }
-    if engine
-        .keyboard_state
-        .pressed_any(&[KeyCode::Right, KeyCode::S])
-    {
-        acceleration.x += MAXIMUM_ACCELERATION;
+    for effect in effects {
+        apply_effect(
+            engine, game_state, &world, effect,
+        );
     }
-    game_state.velocity += acceleration.normalize_or_zero()
-        * MAXIMUM_ACCELERATION;
-    let player = engine
-        .sprites
-        .get_mut("player")
-        .unwrap();
-    player.translation +=
-        game_state.velocity * engine.delta_f32;
+}
 
-    player.rotation = if game_state
-        .velocity
-        .abs()
-        .max_element()
-        == 0.0
-    {
-        0.0
-    } else {
-        f32::atan(
-            game_state
-                .velocity
-                .y
-                / game_state
-                    .velocity
-                    .x,
-        ) + ((game_state
-            .velocity
-            .x
-            < 0.0) as u32 as f32)
-            * std::f32::consts::PI
+/// Snapshot of this frame's keyboard, mouse and timing.
+///
+/// Every player steers with their own keys, and the first player's
+/// keys control the game. While `typing`, letter keys only type.
+fn read_input(
+    engine: &Engine,
+    players: &[Player],
+    typing: bool,
+) -> Input {
+    let keyboard_state = &engine.keyboard_state;
+    let letter = |key: KeyCode| {
+        key_name(key)
+            .filter(|name| name.len() == 1)
+            .and_then(
+                |name| {
+                    name.chars()
+                        .next()
+                },
+            )
+            .filter(char::is_ascii_alphabetic)
     };
-
-    // handle mouse
-    if engine
-        .mouse_state
-        .just_pressed(MouseButton::Left)
+    let just_pressed = |action| {
+        players[0]
+            .key_bindings
+            .keys(action)
+            .iter()
+            .filter(
+                |&&key| !typing || letter(key).is_none(),
+            )
+            .any(|&key| keyboard_state.just_pressed(key))
+    };
+    let mut steering = [Steering::default(); MAX_PLAYERS];
+    for (steering, player) in steering
+        .iter_mut()
+        .zip(players)
     {
-        if let Some(mouse_location) = engine
+        let pressed = |action| {
+            player
+                .key_bindings
+                .pressed(
+                    keyboard_state,
+                    action,
+                )
+        };
+        *steering = Steering {
+            up: pressed(Action::MoveUp),
+            down: pressed(Action::MoveDown),
+            left: pressed(Action::MoveLeft),
+            right: pressed(Action::MoveRight),
+        };
+    }
+    Input {
+        steering,
+        quit: just_pressed(Action::Quit),
+        reset: just_pressed(Action::Reset),
+        start: just_pressed(Action::Start),
+        pause: just_pressed(Action::Pause),
+        letter: if typing {
+            LETTER_KEYS
+                .into_iter()
+                .find(
+                    |&key| keyboard_state.just_pressed(key),
+                )
+                .and_then(letter)
+        } else {
+            None
+        },
+        erase: typing
+            && keyboard_state.just_pressed(KeyCode::Back),
+        click: if engine
             .mouse_state
-            .location()
+            .just_pressed(MouseButton::Left)
         {
-            let feris_label = format!(
-                "feris{}",
-                game_state.feris_index
-            );
-            game_state.feris_index += 1;
-            let feris = engine.add_sprite(
-                feris_label.clone(),
-                "cuddly-feris.png",
-            );
-            feris.translation = mouse_location;
-            feris.scale = 0.4;
-            feris.collision = true;
-        }
-    }
-
-    // spawn new feris's
-    if game_state
-        .spawn_timer
-        .tick(engine.delta)
-        .just_finished()
-    {
-        let feris_label = format!(
-            "feris{}",
-            game_state.feris_index
-        );
-        game_state.feris_index += 1;
-        let feris = engine.add_sprite(
-            feris_label.clone(),
-            "cuddly-feris.png",
-        );
-        feris
-            .translation
-            .x = thread_rng().gen_range(-550.0..550.0);
-        feris
-            .translation
-            .y = thread_rng().gen_range(-305.0..305.0);
-        feris.scale = 0.4;
-        feris.collision = true;
-        engine
-            .audio_manager
-            .play_sfx(
-                SfxPreset::Minimize1,
-                0.5,
-            );
+            engine
+                .mouse_state
+                .location()
+        } else {
+            None
+        },
+        delta: engine.delta_f32,
+        window: engine.window_dimensions,
     }
+}
 
-    // reset game
-    if engine
-        .keyboard_state
-        .just_pressed(KeyCode::G)
-    {
-        game_state.score = Score::default();
-        game_state.velocity = Vec2::new(
-            0.0, 0.0,
-        );
-        engine
-            .sprites
-            .get_mut("player")
-            .unwrap()
-            .translation = Vec2::new(
-            0.0, 0.0,
-        );
-        engine
-            .sprites
-            .clone()
-            .into_keys()
-            .for_each(
+/// The simulation's view of the engine's sprites, with a car for each
+/// of `state`'s players and the feris and power-ups in its registry.
+fn read_world(engine: &Engine, state: &GameState) -> World {
+    let kind = |label: &str| {
+        state
+            .entities
+            .get(label)
+            .map(|id| id.kind())
+    };
+    World {
+        players: PLAYER_LABELS[..state
+            .players
+            .len()]
+            .iter()
+            .map(
                 |label| {
-                    if label != "player".to_string() {
-                        engine.sprites.remove(&label);
+                    let car = &engine.sprites[*label];
+                    Body {
+                        rotation: car.rotation,
+                        ..Body::car(
+                            car.translation,
+                            car.scale,
+                        )
                     }
                 },
+            )
+            .collect(),
+        feris: engine
+            .sprites
+            .values()
+            .filter(
+                |sprite| {
+                    kind(&sprite.label)
+                        == Some(Entity::Feris)
+                },
+            )
+            .map(
+                |sprite| {
+                    (
+                        sprite
+                            .label
+                            .clone(),
+                        Body::feris(
+                            sprite.translation,
+                            sprite.scale,
+                        ),
+                    )
+                },
+            )
+            .collect(),
+        powerups: engine
+            .sprites
+            .values()
+            .filter(
+                |sprite| {
+                    matches!(
+                        kind(&sprite.label),
+                        Some(Entity::PowerUp(_))
+                    )
+                },
+            )
+            .map(
+                |sprite| {
+                    (
+                        sprite
+                            .label
+                            .clone(),
+                        Body::powerup(sprite.translation),
+                    )
+                },
+            )
+            .collect(),
+    }
+}
+
+/// Carry out `effect` of a frame that left the field as `world`.
+fn apply_effect(
+    engine: &mut Engine,
+    game_state: &mut GameState,
+    world: &World,
+    effect: Effect,
+) {
+    match effect {
+        Effect::PlaySfx(sfx, volume) => {
+            engine
+                .audio_manager
+                .play_sfx(
+                    sfx, volume,
+                );
+        }
+        Effect::PlayMusic(preset, volume) => {
+            engine
+                .audio_manager
+                .play_music(
+                    preset, volume,
+                );
+        }
+        Effect::StopMusic => {
+            engine
+                .audio_manager
+                .stop_music();
+        }
+        Effect::SetText { label, value } => {
+            engine
+                .texts
+                .get_mut(label)
+                .unwrap()
+                .value = value;
+        }
+        Effect::Toast(message) => {
+            engine
+                .texts
+                .get_mut("toast")
+                .unwrap()
+                .value = message;
+            game_state.toast_timer = Some(
+                Timer::from_seconds(
+                    TOAST_SECONDS,
+                    TimerMode::Once,
+                ),
             );
-        let score = engine
-            .texts
-            .get_mut("score")
-            .unwrap();
-        score.value = game_state
-            .score
-            .to_string()
-            .to_string();
+        }
+        Effect::SpawnSprite {
+            label,
+            filepath,
+            translation,
+            scale,
+        } => {
+            let sprite = engine.add_sprite(
+                label, filepath,
+            );
+            sprite.translation = translation;
+            sprite.scale = scale;
+        }
+        Effect::ScaleSprite { label, scale } => {
+            if let Some(sprite) = engine
+                .sprites
+                .get_mut(&label)
+            {
+                sprite.scale = scale;
+            }
+        }
+        Effect::MoveSprite { label, translation } => {
+            if let Some(sprite) = engine
+                .sprites
+                .get_mut(&label)
+            {
+                sprite.translation = translation;
+            }
+        }
+        Effect::RemoveSprite(label) => {
+            engine
+                .sprites
+                .remove(&label);
+        }
+        Effect::SaveHighScore => {
+            game_state.save_high_score();
+        }
+        Effect::SaveLeaderboard => {
+            game_state.save_leaderboard();
+        }
+        Effect::SaveGame => {
+            game_state.save_game(world);
+        }
+        Effect::Exit => {
+            game_state.save_high_score();
+            engine.should_exit = true;
+        }
     }
 }
@@ -0,0 +1,283 @@
+//! How the player's car moves.
+//!
+//! The defaults can be replaced by a TOML file setting any of the
+//! fields of [`Physics`]:
+//!
+//! ```toml
+//! max_speed = 350.0
+//! edge = "wrap"
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use rusty_engine::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PhysicsError {
+    #[error("could not read physics: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid physics: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("{0} must be a finite number")]
+    NotFinite(&'static str),
+    #[error("{0} must not be negative")]
+    Negative(&'static str),
+}
+
+/// What happens when the car reaches the edge of the window.
+#[derive(
+    Clone, Copy, Debug, Deserialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    /// Stop at the edge.
+    Clamp,
+    /// Bounce back off the edge.
+    Bounce,
+    /// Leave the window and come back in on the opposite side.
+    Wrap,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Physics {
+    /// Speed gained per second while steering, in pixels per second.
+    pub acceleration: f32,
+    /// Fraction of the speed lost per second.
+    pub drag: f32,
+    /// Top speed in pixels per second.
+    pub max_speed: f32,
+    pub edge: Edge,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            acceleration: 800.0,
+            drag: 1.0,
+            max_speed: 500.0,
+            edge: Edge::Bounce,
+        }
+    }
+}
+
+impl Physics {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, PhysicsError> {
+        let physics: Self =
+            toml::from_str(&fs::read_to_string(path)?)?;
+        physics.validate()?;
+        Ok(physics)
+    }
+
+    fn validate(&self) -> Result<(), PhysicsError> {
+        for (name, value) in [
+            (
+                "acceleration",
+                self.acceleration,
+            ),
+            (
+                "drag", self.drag,
+            ),
+            (
+                "max_speed",
+                self.max_speed,
+            ),
+        ] {
+            if !value.is_finite() {
+                return Err(PhysicsError::NotFinite(name));
+            }
+            if value < 0.0 {
+                return Err(PhysicsError::Negative(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Velocity after steering towards `direction` for `delta`
+    /// seconds, slowed by drag and capped at the top speed.
+    pub fn accelerate(
+        &self,
+        velocity: Vec2,
+        direction: Vec2,
+        delta: f32,
+    ) -> Vec2 {
+        let velocity = velocity
+            + direction.normalize_or_zero()
+                * self.acceleration
+                * delta;
+        (velocity * (-self.drag * delta).exp())
+            .clamp_length_max(self.max_speed)
+    }
+
+    /// Keep a body of `radius` inside a window of `window` size,
+    /// centered on the origin.
+    pub fn confine(
+        &self,
+        translation: &mut Vec2,
+        velocity: &mut Vec2,
+        radius: f32,
+        window: Vec2,
+    ) {
+        for axis in 0..2 {
+            let limit = window[axis] / 2.0 - radius;
+            let side = translation[axis].signum();
+            match self.edge {
+                Edge::Clamp | Edge::Bounce
+                    if translation[axis].abs() > limit =>
+                {
+                    translation[axis] =
+                        side * limit.max(0.0);
+                    // Only ever push the car back inside
+                    velocity[axis] =
+                        if self.edge == Edge::Bounce {
+                            -side * velocity[axis].abs()
+                        } else {
+                            0.0
+                        };
+                }
+                // Wrap once the car is fully out of sight
+                Edge::Wrap
+                    if translation[axis].abs()
+                        > window[axis] / 2.0 + radius =>
+                {
+                    translation[axis] -= side
+                        * (window[axis] + 2.0 * radius);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WINDOW: Vec2 = Vec2::new(
+        1280.0, 720.0,
+    );
+
+    #[test]
+    fn settings_must_be_finite_and_not_negative() {
+        let validate = |toml: &str| {
+            toml::from_str::<Physics>(toml)
+                .unwrap()
+                .validate()
+        };
+        assert!(validate("").is_ok());
+        for (toml, field) in [
+            (
+                "acceleration = nan",
+                "acceleration",
+            ),
+            (
+                "drag = inf",
+                "drag",
+            ),
+            (
+                "max_speed = -inf",
+                "max_speed",
+            ),
+        ] {
+            assert!(
+                matches!(
+                    validate(toml),
+                    Err(PhysicsError::NotFinite(name))
+                        if name == field
+                ),
+                "{}",
+                toml
+            );
+        }
+        assert!(
+            matches!(
+                validate("drag = -1.0"),
+                Err(PhysicsError::Negative("drag"))
+            )
+        );
+    }
+
+    #[test]
+    fn speed_is_capped() {
+        let physics = Physics::default();
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..600 {
+            velocity = physics.accelerate(
+                velocity,
+                Vec2::X,
+                1.0 / 60.0,
+            );
+        }
+        assert!(
+            velocity.length() <= physics.max_speed + 0.001
+        );
+    }
+
+    #[test]
+    fn drag_stops_the_car() {
+        let physics = Physics::default();
+        let mut velocity = Vec2::new(
+            300.0, 0.0,
+        );
+        for _ in 0..600 {
+            velocity = physics.accelerate(
+                velocity,
+                Vec2::ZERO,
+                1.0 / 60.0,
+            );
+        }
+        assert!(velocity.length() < 1.0);
+    }
+
+    #[test]
+    fn edges() {
+        let confine = |edge| {
+            let mut translation = Vec2::new(
+                700.0, 0.0,
+            );
+            let mut velocity = Vec2::new(
+                100.0, 0.0,
+            );
+            Physics {
+                edge,
+                ..Default::default()
+            }
+            .confine(
+                &mut translation,
+                &mut velocity,
+                30.0,
+                WINDOW,
+            );
+            (
+                translation,
+                velocity,
+            )
+        };
+        assert_eq!(
+            confine(Edge::Clamp),
+            (
+                Vec2::new(610.0, 0.0),
+                Vec2::ZERO
+            )
+        );
+        assert_eq!(
+            confine(Edge::Bounce),
+            (
+                Vec2::new(610.0, 0.0),
+                Vec2::new(-100.0, 0.0)
+            )
+        );
+        assert_eq!(
+            confine(Edge::Wrap),
+            (
+                Vec2::new(-640.0, 0.0),
+                Vec2::new(100.0, 0.0)
+            )
+        );
+    }
+}
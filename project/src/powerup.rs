@@ -0,0 +1,126 @@
+//! Pickups that change the game for a few seconds.
+//!
+//! Each kind turns up on its own schedule once the game reaches its
+//! first level, and lies on the field for a while before vanishing.
+//! Whoever drives over it gets its effect:
+//!
+//! - [`PowerUp::Magnet`]: nearby feris drift towards the car.
+//! - [`PowerUp::Speed`]: the car accelerates harder and goes faster.
+//! - [`PowerUp::Doubler`]: every feris is worth twice the points.
+//! - [`PowerUp::Shrink`]: the car shrinks to half its size, which
+//!   makes feris harder to catch.
+
+use locale::Locale;
+use serde::{Deserialize, Serialize};
+
+/// Distance within which a magnet pulls feris in.
+pub const MAGNET_RADIUS: f32 = 300.0;
+/// Speed of a feris being pulled in, in pixels per second.
+pub const MAGNET_SPEED: f32 = 250.0;
+/// Factor applied to the acceleration and top speed of a boosted car.
+pub const SPEED_BOOST: f32 = 1.5;
+/// Scale of a shrunk car, relative to its usual scale.
+pub const SHRINK_SCALE: f32 = 0.5;
+/// Seconds a power-up lies on the field if nobody picks it up.
+pub const LIFETIME: f32 = 5.0;
+/// Scale of a power-up sprite.
+pub const SCALE: f32 = 1.0;
+/// Collision radius of a power-up sprite at scale 1.0.
+pub const RADIUS: f32 = 20.0;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUp {
+    Magnet,
+    Speed,
+    Doubler,
+    Shrink,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 4] = [
+        PowerUp::Magnet,
+        PowerUp::Speed,
+        PowerUp::Doubler,
+        PowerUp::Shrink,
+    ];
+
+    /// Start of the sprite labels of this kind, which are followed by
+    /// a number.
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUp::Magnet => "magnet",
+            PowerUp::Speed => "speed",
+            PowerUp::Doubler => "doubler",
+            PowerUp::Shrink => "shrink",
+        }
+    }
+
+    /// Image of the sprite, from rusty_engine's assets.
+    pub fn image(self) -> &'static str {
+        match self {
+            PowerUp::Magnet => {
+                "sprite/rolling/ball_blue.png"
+            }
+            PowerUp::Speed => "sprite/rolling/ball_red.png",
+            PowerUp::Doubler => {
+                "sprite/racing/barrel_blue.png"
+            }
+            PowerUp::Shrink => {
+                "sprite/racing/barrel_red.png"
+            }
+        }
+    }
+
+    /// Name shown next to the countdown on the HUD.
+    pub fn name(self, locale: &Locale) -> String {
+        locale.text(
+            match self {
+                PowerUp::Magnet => "power_up_magnet",
+                PowerUp::Speed => "power_up_speed",
+                PowerUp::Doubler => "power_up_doubler",
+                PowerUp::Shrink => "power_up_shrink",
+            },
+        )
+    }
+
+    /// Seconds the effect lasts once picked up.
+    pub fn seconds(self) -> f32 {
+        match self {
+            PowerUp::Magnet => 6.0,
+            PowerUp::Speed => 5.0,
+            PowerUp::Doubler => 8.0,
+            PowerUp::Shrink => 6.0,
+        }
+    }
+
+    /// Seconds between appearances on the field.
+    pub fn interval(self) -> f32 {
+        match self {
+            PowerUp::Magnet => 15.0,
+            PowerUp::Speed => 11.0,
+            PowerUp::Doubler => 23.0,
+            PowerUp::Shrink => 17.0,
+        }
+    }
+
+    /// Index of the first difficulty level this kind appears at.
+    pub fn first_level(self) -> usize {
+        match self {
+            PowerUp::Magnet | PowerUp::Speed => 0,
+            PowerUp::Doubler => 1,
+            PowerUp::Shrink => 2,
+        }
+    }
+}
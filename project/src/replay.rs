@@ -0,0 +1,789 @@
+//! Recording every frame's [`Input`] and playing it back.
+//!
+//! A replay file starts with [`MAGIC`], a format version, the spawn
+//! seed, the number of players and the leaderboard the game started
+//! with, as a `u32` byte count and then JSON. Each frame follows as
+//! two flag bytes and the frame's delta, then the click location if
+//! the mouse was clicked, the window size if it changed since the
+//! previous frame and the letter if one was typed. Every number is a
+//! little-endian `f32`, apart from the `u64` seed, the `u8` player
+//! count, the `u16` flags and the ASCII letter.
+//!
+//! The leaderboard is kept because it decides whether start begins
+//! another round or takes a name at the end of one. Beyond that only
+//! the input is recorded. The difficulty and physics files are
+//! read afresh when a recording is played back, so it only plays out
+//! the same way while they are as they were when it was recorded.
+
+use std::fs::File;
+use std::io::{
+    self, BufReader, BufWriter, ErrorKind, Read, Write,
+};
+use std::path::Path;
+
+use rusty_engine::prelude::*;
+
+use crate::score::Leaderboard;
+use crate::simulation::{
+    self, Effect, GameState, Input, Steering, World,
+    DEFAULT_WINDOW, LEADERBOARD_SIZE, MAX_PLAYERS,
+};
+
+/// First bytes of every replay file.
+pub const MAGIC: &[u8; 4] = b"FRPL";
+/// Version of the replay format written by [`Recorder`].
+pub const VERSION: u8 = 1;
+
+/// Steering flags of the first player. Each other player's are these
+/// shifted by their entry in `STEERING_SHIFTS`.
+const UP: u16 = 1 << 0;
+const DOWN: u16 = 1 << 1;
+const LEFT: u16 = 1 << 2;
+const RIGHT: u16 = 1 << 3;
+const STEERING_SHIFTS: [u32; MAX_PLAYERS] = [0, 10];
+const QUIT: u16 = 1 << 4;
+const RESET: u16 = 1 << 5;
+const CLICK: u16 = 1 << 6;
+const RESIZE: u16 = 1 << 7;
+const START: u16 = 1 << 8;
+const PAUSE: u16 = 1 << 9;
+const LETTER: u16 = 1 << 14;
+const ERASE: u16 = 1 << 15;
+
+/// A recorded game: the seed it was played with, how many played, the
+/// leaderboard it started with and every frame's input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    pub players: usize,
+    pub leaderboard: Leaderboard,
+    pub frames: Vec<Input>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read_from<R: Read>(
+        mut reader: R,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    "not a replay file",
+                ),
+            );
+        }
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "unsupported replay version {}",
+                        version[0]
+                    ),
+                ),
+            );
+        }
+        let mut seed = [0; 8];
+        reader.read_exact(&mut seed)?;
+        let mut players = [0; 1];
+        reader.read_exact(&mut players)?;
+        let players = usize::from(players[0]);
+        if !(1..=MAX_PLAYERS).contains(&players) {
+            return Err(
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "replay of {} players",
+                        players
+                    ),
+                ),
+            );
+        }
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let length = u32::from_le_bytes(length);
+        // Read through `take` so a bad length can't make us allocate
+        // gigabytes up front.
+        let mut json = Vec::new();
+        reader
+            .by_ref()
+            .take(u64::from(length))
+            .read_to_end(&mut json)?;
+        if json.len() != length as usize {
+            return Err(
+                io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "replay ends inside its leaderboard",
+                ),
+            );
+        }
+        let leaderboard =
+            serde_json::from_slice::<Leaderboard>(&json)?
+                .with_capacity(LEADERBOARD_SIZE);
+        let mut frames = Vec::new();
+        let mut window = DEFAULT_WINDOW;
+        while let Some(input) = read_frame(
+            &mut reader,
+            window,
+        )? {
+            window = input.window;
+            frames.push(input);
+        }
+        Ok(
+            Self {
+                seed: u64::from_le_bytes(seed),
+                players,
+                leaderboard,
+                frames,
+            },
+        )
+    }
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+/// Next frame, or `None` at the end of the file. `window` is the
+/// previous frame's window size.
+fn read_frame<R: Read>(
+    reader: &mut R,
+    window: Vec2,
+) -> io::Result<Option<Input>> {
+    let mut first = [0; 1];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    let mut second = [0; 1];
+    reader.read_exact(&mut second)?;
+    let flags = u16::from_le_bytes([first[0], second[0]]);
+    let delta = read_f32(reader)?;
+    if !delta.is_finite() || delta < 0.0 {
+        return Err(
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "frame delta of {} seconds",
+                    delta
+                ),
+            ),
+        );
+    }
+    let click = if flags & CLICK != 0 {
+        Some(
+            Vec2::new(
+                read_f32(reader)?,
+                read_f32(reader)?,
+            ),
+        )
+    } else {
+        None
+    };
+    let window = if flags & RESIZE != 0 {
+        Vec2::new(
+            read_f32(reader)?,
+            read_f32(reader)?,
+        )
+    } else {
+        window
+    };
+    let letter = if flags & LETTER != 0 {
+        let mut letter = [0; 1];
+        reader.read_exact(&mut letter)?;
+        Some(char::from(letter[0]))
+    } else {
+        None
+    };
+    Ok(Some(
+        Input {
+            steering: STEERING_SHIFTS.map(
+                |shift| {
+                    let flags = flags >> shift;
+                    Steering {
+                        up: flags & UP != 0,
+                        down: flags & DOWN != 0,
+                        left: flags & LEFT != 0,
+                        right: flags & RIGHT != 0,
+                    }
+                },
+            ),
+            quit: flags & QUIT != 0,
+            reset: flags & RESET != 0,
+            start: flags & START != 0,
+            pause: flags & PAUSE != 0,
+            letter,
+            erase: flags & ERASE != 0,
+            click,
+            delta,
+            window,
+        },
+    ))
+}
+
+/// Writes frames to a replay file as they are played, each one
+/// flushed as soon as it is recorded.
+pub struct Recorder<W: Write> {
+    writer: W,
+    /// Window size of the last recorded frame.
+    window: Vec2,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        seed: u64,
+        players: usize,
+        leaderboard: &Leaderboard,
+    ) -> io::Result<Self> {
+        Self::new(
+            BufWriter::new(File::create(path)?),
+            seed,
+            players,
+            leaderboard,
+        )
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording of a game of `players` spawning feris from
+    /// `seed`, with `leaderboard` as it stands.
+    pub fn new(
+        mut writer: W,
+        seed: u64,
+        players: usize,
+        leaderboard: &Leaderboard,
+    ) -> io::Result<Self> {
+        let json = serde_json::to_vec(leaderboard)?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&seed.to_le_bytes())?;
+        writer.write_all(&[players as u8])?;
+        writer.write_all(
+            &(json.len() as u32).to_le_bytes(),
+        )?;
+        writer.write_all(&json)?;
+        Ok(
+            Self {
+                writer,
+                window: DEFAULT_WINDOW,
+            },
+        )
+    }
+
+    pub fn record(
+        &mut self,
+        input: &Input,
+    ) -> io::Result<()> {
+        let steering = input
+            .steering
+            .iter()
+            .zip(STEERING_SHIFTS)
+            .flat_map(
+                |(steering, shift)| {
+                    [
+                        (
+                            steering.up,
+                            UP << shift,
+                        ),
+                        (
+                            steering.down,
+                            DOWN << shift,
+                        ),
+                        (
+                            steering.left,
+                            LEFT << shift,
+                        ),
+                        (
+                            steering.right,
+                            RIGHT << shift,
+                        ),
+                    ]
+                },
+            );
+        let flags = [
+            (
+                input.quit, QUIT,
+            ),
+            (
+                input.reset,
+                RESET,
+            ),
+            (
+                input
+                    .click
+                    .is_some(),
+                CLICK,
+            ),
+            (
+                input.window != self.window,
+                RESIZE,
+            ),
+            (
+                input.start,
+                START,
+            ),
+            (
+                input.pause,
+                PAUSE,
+            ),
+            (
+                input
+                    .letter
+                    .is_some(),
+                LETTER,
+            ),
+            (
+                input.erase,
+                ERASE,
+            ),
+        ]
+        .into_iter()
+        .chain(steering)
+        .filter(|(set, _)| *set)
+        .fold(
+            0_u16,
+            |flags, (_, flag)| flags | flag,
+        );
+        self.writer
+            .write_all(&flags.to_le_bytes())?;
+        self.writer
+            .write_all(
+                &input
+                    .delta
+                    .to_le_bytes(),
+            )?;
+        if let Some(click) = input.click {
+            self.writer
+                .write_all(
+                    &click
+                        .x
+                        .to_le_bytes(),
+                )?;
+            self.writer
+                .write_all(
+                    &click
+                        .y
+                        .to_le_bytes(),
+                )?;
+        }
+        if input.window != self.window {
+            for size in [
+                input
+                    .window
+                    .x,
+                input
+                    .window
+                    .y,
+            ] {
+                self.writer
+                    .write_all(&size.to_le_bytes())?;
+            }
+            self.window = input.window;
+        }
+        if let Some(letter) = input.letter {
+            // Only ASCII letters are typed, so this is lossless
+            self.writer
+                .write_all(&[letter as u8])?;
+        }
+        // Closing the window ends the game without unwinding, so
+        // nothing is left buffered for a drop that never comes
+        self.writer
+            .flush()
+    }
+}
+
+/// Plays a recording back one frame at a time, in place of the
+/// keyboard and mouse.
+pub struct Playback {
+    frames: std::vec::IntoIter<Input>,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            frames: recording
+                .frames
+                .into_iter(),
+        }
+    }
+
+    /// The next recorded frame. Once the recording runs out this quits
+    /// the game.
+    pub fn next_input(&mut self) -> Input {
+        self.frames
+            .next()
+            .unwrap_or(
+                Input {
+                    quit: true,
+                    ..Default::default()
+                },
+            )
+    }
+}
+
+/// Replay `recording` without a window and return the final state.
+///
+/// `state` must be set up the way the recorded game was, starting with
+/// `recording.seed` and `recording.players`. Its leaderboard is
+/// replaced by the recorded one.
+pub fn run(
+    recording: &Recording,
+    mut state: GameState,
+) -> (
+    GameState,
+    World,
+) {
+    state.leaderboard = recording
+        .leaderboard
+        .clone();
+    let mut world = World::new(recording.players);
+    for input in &recording.frames {
+        let effects = simulation::step(
+            &mut state, input, &mut world,
+        );
+        if effects
+            .iter()
+            .any(|effect| matches!(effect, Effect::Exit))
+        {
+            break;
+        }
+    }
+    (
+        state, world,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use key_bindings::KeyBindings;
+
+    use super::*;
+    use crate::score::Entry;
+    use crate::screen::Screen;
+    use crate::simulation::ROUND_SECONDS;
+
+    /// Starts a two player game and clicks feris onto the drifting
+    /// cars for a while, then drives around, resets, pauses and keeps
+    /// playing.
+    fn session() -> Recording {
+        let frames = (0..3_000)
+            .map(
+                |i| Input {
+                    steering: [
+                        Steering {
+                            up: i > 1_000 && i % 400 < 120,
+                            down: i > 1_000
+                                && i % 400 >= 300,
+                            left: i > 1_000
+                                && i % 700 < 200,
+                            right: i > 1_000
+                                && i % 700 >= 450,
+                        },
+                        Steering {
+                            up: i > 1_000 && i % 500 >= 350,
+                            down: i > 1_000
+                                && i % 500 < 100,
+                            left: i > 1_000
+                                && i % 300 < 100,
+                            right: i > 1_000
+                                && i % 300 >= 200,
+                        },
+                    ],
+                    reset: i == 2_000,
+                    start: i == 0,
+                    pause: i == 2_500 || i == 2_600,
+                    letter: (i == 2_900).then_some('A'),
+                    erase: i == 2_950,
+                    window: if i < 1_500 {
+                        DEFAULT_WINDOW
+                    } else {
+                        Vec2::new(
+                            800.0, 600.0,
+                        )
+                    },
+                    click: (i < 1_000 && i % 90 == 0)
+                        .then_some(Vec2::ZERO),
+                    delta: 1.0 / 60.0,
+                    ..Default::default()
+                },
+            )
+            .collect();
+        Recording {
+            seed: 42,
+            players: 2,
+            leaderboard: Leaderboard::new(LEADERBOARD_SIZE),
+            frames,
+        }
+    }
+
+    #[test]
+    fn recording_round_trips() {
+        let recording = session();
+        let mut bytes = Vec::new();
+        let mut recorder = Recorder::new(
+            &mut bytes,
+            recording.seed,
+            recording.players,
+            &recording.leaderboard,
+        )
+        .unwrap();
+        for input in &recording.frames {
+            recorder
+                .record(input)
+                .unwrap();
+        }
+        assert_eq!(
+            Recording::read_from(bytes.as_slice()).unwrap(),
+            recording
+        );
+    }
+
+    #[test]
+    fn frames_are_flushed_as_recorded() {
+        let recording = session();
+        let mut recorder = Recorder::new(
+            BufWriter::new(Vec::new()),
+            recording.seed,
+            recording.players,
+            &recording.leaderboard,
+        )
+        .unwrap();
+        for input in &recording.frames {
+            recorder
+                .record(input)
+                .unwrap();
+        }
+        // Nothing waits on the recorder being dropped
+        let bytes = recorder
+            .writer
+            .get_ref()
+            .clone();
+        assert_eq!(
+            Recording::read_from(bytes.as_slice()).unwrap(),
+            recording
+        );
+    }
+
+    #[test]
+    fn replays_reach_the_same_score() {
+        let recording = session();
+        let replay = || {
+            let mut state =
+                GameState::with_seed(recording.seed);
+            state.set_players(vec![
+                KeyBindings::default();
+                recording.players
+            ]);
+            run(
+                &recording, state,
+            )
+        };
+        let (first, first_world) = replay();
+        let (second, second_world) = replay();
+        assert!(
+            first
+                .high_score
+                .value
+                > 0
+        );
+        assert_eq!(
+            first
+                .high_score
+                .value,
+            second
+                .high_score
+                .value
+        );
+        for (first, second) in first
+            .players
+            .iter()
+            .zip(&second.players)
+        {
+            assert_eq!(
+                first
+                    .score
+                    .value,
+                second
+                    .score
+                    .value
+            );
+        }
+        assert_eq!(
+            first_world,
+            second_world
+        );
+    }
+
+    #[test]
+    fn replays_keep_to_the_recorded_leaderboard() {
+        let mut full = Leaderboard::new(LEADERBOARD_SIZE);
+        for _ in 0..LEADERBOARD_SIZE {
+            full.insert(
+                Entry {
+                    name: "TOP".to_string(),
+                    score: 1_000,
+                    date: "2024-05-01".to_string(),
+                    seconds: 90.0,
+                },
+            );
+        }
+        // A round of clicking feris onto the car, then start again
+        let round = (ROUND_SECONDS * 60.0) as usize + 60;
+        let frames = (0..round + 2)
+            .map(
+                |i| Input {
+                    start: i == 0 || i == round,
+                    click: (i < 1_000 && i % 90 == 0)
+                        .then_some(Vec2::ZERO),
+                    delta: 1.0 / 60.0,
+                    ..Default::default()
+                },
+            )
+            .collect();
+        let recording = Recording {
+            seed: 42,
+            players: 1,
+            leaderboard: full,
+            frames,
+        };
+        let mut bytes = Vec::new();
+        let mut recorder = Recorder::new(
+            &mut bytes,
+            recording.seed,
+            recording.players,
+            &recording.leaderboard,
+        )
+        .unwrap();
+        for input in &recording.frames {
+            recorder
+                .record(input)
+                .unwrap();
+        }
+        let read =
+            Recording::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(
+            read,
+            recording
+        );
+
+        // Played back where the leaderboard is empty, start still
+        // begins another round rather than taking a name
+        let (state, _) = run(
+            &read,
+            GameState::with_seed(read.seed),
+        );
+        assert!(
+            state
+                .high_score
+                .value
+                > 0
+        );
+        assert!(state
+            .name_entry
+            .is_none());
+        assert_eq!(
+            state.screen,
+            Screen::Playing
+        );
+    }
+
+    #[test]
+    fn reads_the_written_layout() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(7_u64.to_le_bytes());
+        bytes.push(1);
+        let board = br#"{"entries":[]}"#;
+        bytes.extend((board.len() as u32).to_le_bytes());
+        bytes.extend(board);
+        bytes.extend((UP | LETTER).to_le_bytes());
+        bytes.extend(0.5_f32.to_le_bytes());
+        bytes.push(b'Q');
+        assert_eq!(
+            Recording::read_from(bytes.as_slice()).unwrap(),
+            Recording {
+                seed: 7,
+                players: 1,
+                leaderboard: Leaderboard::new(
+                    LEADERBOARD_SIZE
+                ),
+                frames: vec![Input {
+                    steering: [
+                        Steering {
+                            up: true,
+                            ..Default::default()
+                        },
+                        Steering::default(),
+                    ],
+                    letter: Some('Q'),
+                    delta: 0.5,
+                    ..Default::default()
+                }],
+            }
+        );
+
+        bytes[MAGIC.len()] = VERSION + 1;
+        assert_eq!(
+            Recording::read_from(bytes.as_slice())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_deltas_and_short_leaderboards() {
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        header.extend(7_u64.to_le_bytes());
+        header.push(1);
+        let board = br#"{"entries":[]}"#;
+        let mut bytes = header.clone();
+        bytes.extend((board.len() as u32).to_le_bytes());
+        bytes.extend(board);
+        for delta in [-0.5, f32::NAN, f32::INFINITY] {
+            let mut frame = bytes.clone();
+            frame.extend(UP.to_le_bytes());
+            frame.extend(delta.to_le_bytes());
+            assert_eq!(
+                Recording::read_from(frame.as_slice())
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::InvalidData
+            );
+        }
+
+        header.extend(u32::MAX.to_le_bytes());
+        header.extend(board);
+        assert_eq!(
+            Recording::read_from(header.as_slice())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(
+            Recording::read_from(
+                &b"PNG\0\0\0\0\0\0\0\0\0\0"[..]
+            )
+            .unwrap_err()
+            .kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,834 @@
+//! A round in progress, saved on quitting to carry on with later.
+//!
+//! The save is a JSON object starting with a `version`. Saves from
+//! another version are refused as a whole rather than half-read, and
+//! so is any save naming a sprite the game doesn't know about.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use entities::{Kind, Registry};
+use rusty_engine::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::entity::{self, Entity, EntityId};
+use crate::powerup::{self, PowerUp};
+use crate::simulation::{
+    Body, GameState, Lifetime, World, FERIS_LIFETIME,
+    MAX_PLAYERS, PLAYER_LABELS,
+};
+use crate::steering::{Behavior, Motion};
+
+/// Version written into new saves, and the only one read back.
+pub const VERSION: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("could not read save: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid save: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("save is version {0}, but this game only reads version {VERSION}")]
+    Version(u32),
+    #[error("save has {0} players, but there can be 1 to {MAX_PLAYERS}")]
+    Players(usize),
+    #[error("save has no sprite for {0}")]
+    MissingSprite(&'static str),
+    #[error("save has a sprite {0:?} that is neither a car, a feris nor a power-up")]
+    UnknownSprite(String),
+    #[error("save numbers {0:?} past the last entity number")]
+    EntityNumber(String),
+}
+
+/// Just enough of a save to check its version before reading the rest.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// Everything needed to put a round back the way it was.
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+pub struct SaveGame {
+    pub version: u32,
+    pub high_score: u32,
+    /// One per car, in the order of [`PLAYER_LABELS`].
+    pub players: Vec<PlayerSave>,
+    /// Number the next feris or power-up of each kind gets, by the
+    /// start of its labels.
+    pub next: BTreeMap<String, u32>,
+    /// Seconds since the last feris spawn.
+    pub spawn_elapsed: f32,
+    /// Seconds played in the round.
+    pub round_elapsed: f32,
+    /// Seconds since each kind of power-up last turned up.
+    pub powerup_elapsed: BTreeMap<PowerUp, f32>,
+    /// Seconds each feris has been on the field, by label.
+    pub feris: BTreeMap<String, FerisSave>,
+    /// Seconds each power-up has been on the field, by label.
+    pub pickups: BTreeMap<String, f32>,
+    /// Every car, feris and power-up sprite.
+    pub sprites: Vec<SpriteSave>,
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+pub struct PlayerSave {
+    pub score: u32,
+    pub velocity: [f32; 2],
+    /// Seconds each power-up in effect has been running.
+    pub powerups: BTreeMap<PowerUp, f32>,
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+pub struct FerisSave {
+    pub elapsed: f32,
+    /// Scale the feris spawned with, before it started shrinking.
+    pub scale: f32,
+    /// How it moves by itself, standing still in older saves.
+    #[serde(default)]
+    pub behavior: Behavior,
+    #[serde(default)]
+    pub velocity: [f32; 2],
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+pub struct SpriteSave {
+    pub label: String,
+    pub translation: [f32; 2],
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl SpriteSave {
+    fn new(label: &str, body: &Body) -> Self {
+        Self {
+            label: label.to_string(),
+            translation: body
+                .translation
+                .into(),
+            rotation: body.rotation,
+            scale: body.scale,
+        }
+    }
+}
+
+impl SaveGame {
+    /// Save of the round `state` is playing on `world`.
+    pub fn capture(
+        state: &GameState,
+        world: &World,
+    ) -> Self {
+        Self {
+            version: VERSION,
+            high_score: state
+                .high_score
+                .value,
+            players: state
+                .players
+                .iter()
+                .map(
+                    |player| PlayerSave {
+                        score: player
+                            .score
+                            .value,
+                        velocity: player
+                            .velocity
+                            .into(),
+                        powerups: elapsed(&player.powerups),
+                    },
+                )
+                .collect(),
+            next: Entity::ALL
+                .iter()
+                .map(
+                    |&kind| {
+                        (
+                            kind.name()
+                                .to_string(),
+                            state
+                                .entities
+                                .next(kind),
+                        )
+                    },
+                )
+                .collect(),
+            spawn_elapsed: state
+                .spawn_timer
+                .elapsed_secs(),
+            round_elapsed: state
+                .round_timer
+                .elapsed_secs(),
+            powerup_elapsed: elapsed(&state.powerup_timers),
+            feris: state
+                .lifetimes
+                .iter()
+                .map(
+                    |(label, lifetime)| {
+                        let motion = state
+                            .motions
+                            .get(label)
+                            .copied()
+                            .unwrap_or_default();
+                        (
+                            label.clone(),
+                            FerisSave {
+                                elapsed: lifetime
+                                    .timer
+                                    .elapsed_secs(),
+                                scale: lifetime.scale,
+                                behavior: motion.behavior,
+                                velocity: motion
+                                    .velocity
+                                    .into(),
+                            },
+                        )
+                    },
+                )
+                .collect(),
+            pickups: elapsed(&state.pickups),
+            sprites: world
+                .bodies()
+                .map(
+                    |(label, body)| {
+                        SpriteSave::new(
+                            label, body,
+                        )
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, SaveError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parse a save, refusing one written by another version.
+    pub fn from_json(
+        text: &str,
+    ) -> Result<Self, SaveError> {
+        let header: Header = serde_json::from_str(text)?;
+        if header.version != VERSION {
+            return Err(SaveError::Version(header.version));
+        }
+        let save: Self = serde_json::from_str(text)?;
+        let players = save
+            .players
+            .len();
+        if !(1..=MAX_PLAYERS).contains(&players) {
+            return Err(SaveError::Players(players));
+        }
+        Ok(save)
+    }
+
+    /// Save to `path` as JSON, creating its parent directories if
+    /// needed.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            self.to_json()?,
+        )?;
+        Ok(())
+    }
+
+    /// Put the saved round into `state`, which must already have a
+    /// player for each saved one, and return the field it was played
+    /// on. Nothing is changed if the save doesn't fit together.
+    pub fn restore(
+        self,
+        state: &mut GameState,
+    ) -> Result<World, SaveError> {
+        let players = self
+            .players
+            .len();
+        assert_eq!(
+            players,
+            state
+                .players
+                .len(),
+            "players in the save and the game"
+        );
+        // Nothing could be numbered after these
+        if let Some(label) = self
+            .sprites
+            .iter()
+            .map(|sprite| &sprite.label)
+            .filter(
+                |label| {
+                    EntityId::from_label(label).is_some_and(
+                        |id| id.number() == u32::MAX,
+                    )
+                },
+            )
+            .chain(
+                self.next
+                    .iter()
+                    .filter(|&(_, &next)| next == u32::MAX)
+                    .map(|(name, _)| name),
+            )
+            .next()
+        {
+            return Err(
+                SaveError::EntityNumber(label.clone()),
+            );
+        }
+        let mut world = World::new(players);
+        let mut found = [false; MAX_PLAYERS];
+        for sprite in &self.sprites {
+            let body = |body: Body| Body {
+                rotation: sprite.rotation,
+                ..body
+            };
+            let translation =
+                Vec2::from(sprite.translation);
+            if let Some(index) =
+                world.player_index(&sprite.label)
+            {
+                found[index] = true;
+                world.players[index] = body(
+                    Body::car(
+                        translation,
+                        sprite.scale,
+                    ),
+                );
+                continue;
+            }
+            match entity::kind_of(&sprite.label) {
+                Some(Entity::Feris)
+                    if self
+                        .feris
+                        .contains_key(&sprite.label) =>
+                {
+                    world
+                        .feris
+                        .insert(
+                            sprite
+                                .label
+                                .clone(),
+                            body(
+                                Body::feris(
+                                    translation,
+                                    sprite.scale,
+                                ),
+                            ),
+                        );
+                }
+                Some(Entity::PowerUp(_))
+                    if self
+                        .pickups
+                        .contains_key(&sprite.label) =>
+                {
+                    world
+                        .powerups
+                        .insert(
+                            sprite
+                                .label
+                                .clone(),
+                            body(
+                                Body::powerup(translation),
+                            ),
+                        );
+                }
+                _ => {
+                    return Err(
+                        SaveError::UnknownSprite(
+                            sprite
+                                .label
+                                .clone(),
+                        ),
+                    );
+                }
+            }
+        }
+        if let Some(index) = found[..players]
+            .iter()
+            .position(|found| !found)
+        {
+            return Err(
+                SaveError::MissingSprite(
+                    PLAYER_LABELS[index],
+                ),
+            );
+        }
+        // Feris and power-ups without a sprite were already gone
+        let feris: BTreeMap<_, _> = self
+            .feris
+            .into_iter()
+            .filter(
+                |(label, _)| {
+                    world
+                        .feris
+                        .contains_key(label)
+                },
+            )
+            .collect();
+        let pickups: BTreeMap<_, _> = self
+            .pickups
+            .into_iter()
+            .filter(
+                |(label, _)| {
+                    world
+                        .powerups
+                        .contains_key(label)
+                },
+            )
+            .collect();
+
+        state
+            .high_score
+            .value = state
+            .high_score
+            .value
+            .max(self.high_score);
+        for (player, saved) in state
+            .players
+            .iter_mut()
+            .zip(self.players)
+        {
+            player
+                .score
+                .reset();
+            player
+                .score
+                .value = saved.score;
+            player.velocity = saved
+                .velocity
+                .into();
+            player.powerups = saved
+                .powerups
+                .into_iter()
+                .map(
+                    |(power_up, elapsed)| {
+                        (
+                            power_up,
+                            timer(
+                                power_up.seconds(),
+                                elapsed,
+                                TimerMode::Once,
+                            ),
+                        )
+                    },
+                )
+                .collect();
+        }
+        state.entities = Registry::new();
+        for id in world
+            .feris
+            .keys()
+            .chain(
+                world
+                    .powerups
+                    .keys(),
+            )
+            .filter_map(|label| EntityId::from_label(label))
+        {
+            state
+                .entities
+                .adopt(id);
+        }
+        for &kind in Entity::ALL {
+            if let Some(&next) = self
+                .next
+                .get(kind.name())
+            {
+                state
+                    .entities
+                    .set_next(
+                        kind, next,
+                    );
+            }
+        }
+        state.level = state
+            .difficulty
+            .level_for(state.top_score());
+        state.spawn_timer = timer(
+            state
+                .difficulty
+                .level(state.level)
+                .spawn_interval,
+            self.spawn_elapsed,
+            TimerMode::Repeating,
+        );
+        set_elapsed(
+            &mut state.round_timer,
+            self.round_elapsed,
+        );
+        for (power_up, timer) in &mut state.powerup_timers {
+            set_elapsed(
+                timer,
+                self.powerup_elapsed
+                    .get(power_up)
+                    .copied()
+                    .unwrap_or(0.0),
+            );
+        }
+        state.motions = feris
+            .iter()
+            .map(
+                |(label, saved)| {
+                    (
+                        label.clone(),
+                        Motion {
+                            behavior: saved.behavior,
+                            velocity: saved
+                                .velocity
+                                .into(),
+                        },
+                    )
+                },
+            )
+            .collect();
+        state.lifetimes = feris
+            .into_iter()
+            .map(
+                |(label, saved)| {
+                    (
+                        label,
+                        Lifetime {
+                            timer: timer(
+                                FERIS_LIFETIME,
+                                saved.elapsed,
+                                TimerMode::Once,
+                            ),
+                            scale: saved.scale,
+                        },
+                    )
+                },
+            )
+            .collect();
+        state.pickups = pickups
+            .into_iter()
+            .map(
+                |(label, elapsed)| {
+                    (
+                        label,
+                        timer(
+                            powerup::LIFETIME,
+                            elapsed,
+                            TimerMode::Once,
+                        ),
+                    )
+                },
+            )
+            .collect();
+        state
+            .contacts
+            .clear();
+        Ok(world)
+    }
+
+    /// Serialize as pretty JSON.
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Seconds each of `timers` has been running.
+fn elapsed<K: Clone + Ord>(
+    timers: &BTreeMap<K, Timer>,
+) -> BTreeMap<K, f32> {
+    timers
+        .iter()
+        .map(
+            |(key, timer)| {
+                (
+                    key.clone(),
+                    timer.elapsed_secs(),
+                )
+            },
+        )
+        .collect()
+}
+
+/// Timer of `seconds` that has been running for `elapsed`.
+fn timer(
+    seconds: f32,
+    elapsed: f32,
+    mode: TimerMode,
+) -> Timer {
+    let mut timer = Timer::from_seconds(
+        seconds, mode,
+    );
+    set_elapsed(
+        &mut timer, elapsed,
+    );
+    timer
+}
+
+/// Wind `timer` on by `elapsed` seconds, kept within its duration.
+fn set_elapsed(timer: &mut Timer, elapsed: f32) {
+    let elapsed = elapsed.clamp(
+        0.0,
+        timer
+            .duration()
+            .as_secs_f32(),
+    );
+    timer.set_elapsed(Duration::from_secs_f32(elapsed));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::screen::Screen;
+    use crate::simulation::{self, Input};
+
+    /// A round a couple of seconds in, with a feris and a power-up on
+    /// the field and a power-up in effect.
+    fn round() -> (
+        GameState,
+        World,
+    ) {
+        let mut state = GameState::default();
+        let mut world = World::default();
+        simulation::step(
+            &mut state,
+            &Input {
+                start: true,
+                ..Default::default()
+            },
+            &mut world,
+        );
+        simulation::step(
+            &mut state,
+            &Input {
+                click: Some(
+                    Vec2::new(
+                        400.0, 200.0,
+                    ),
+                ),
+                delta: 1.0 / 60.0,
+                ..Default::default()
+            },
+            &mut world,
+        );
+        let magnet = state
+            .entities
+            .spawn(Entity::PowerUp(PowerUp::Magnet))
+            .label();
+        world
+            .powerups
+            .insert(
+                magnet.clone(),
+                Body::powerup(
+                    Vec2::new(
+                        -300.0, 100.0,
+                    ),
+                ),
+            );
+        state
+            .pickups
+            .insert(
+                magnet,
+                Timer::from_seconds(
+                    powerup::LIFETIME,
+                    TimerMode::Once,
+                ),
+            );
+        state.players[0]
+            .powerups
+            .insert(
+                PowerUp::Speed,
+                Timer::from_seconds(
+                    PowerUp::Speed.seconds(),
+                    TimerMode::Once,
+                ),
+            );
+        state.players[0]
+            .score
+            .value = 7;
+        for _ in 0..90 {
+            simulation::step(
+                &mut state,
+                &Input {
+                    delta: 1.0 / 60.0,
+                    ..Default::default()
+                },
+                &mut world,
+            );
+        }
+        (
+            state, world,
+        )
+    }
+
+    #[test]
+    fn rounds_resume_where_they_were_saved() {
+        let (state, world) = round();
+        let json = SaveGame::capture(
+            &state, &world,
+        )
+        .to_json()
+        .unwrap();
+
+        let mut resumed = GameState::default();
+        let resumed_world = SaveGame::from_json(&json)
+            .unwrap()
+            .restore(&mut resumed)
+            .unwrap();
+        assert_eq!(
+            resumed_world,
+            world
+        );
+        assert_eq!(
+            resumed.players[0]
+                .score
+                .value,
+            7
+        );
+        assert!(resumed.players[0].has(PowerUp::Speed));
+        assert!(
+            resumed
+                .entities
+                .iter()
+                .eq(state
+                    .entities
+                    .iter())
+        );
+        for &kind in Entity::ALL {
+            assert_eq!(
+                resumed
+                    .entities
+                    .next(kind),
+                state
+                    .entities
+                    .next(kind)
+            );
+        }
+        assert_eq!(
+            resumed
+                .lifetimes
+                .keys()
+                .collect::<Vec<_>>(),
+            state
+                .lifetimes
+                .keys()
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            (resumed
+                .round_timer
+                .elapsed_secs()
+                - state
+                    .round_timer
+                    .elapsed_secs())
+            .abs()
+                < 0.001
+        );
+
+        simulation::resume(
+            &mut resumed,
+            &resumed_world,
+        );
+        assert_eq!(
+            resumed.screen,
+            Screen::Paused
+        );
+    }
+
+    #[test]
+    fn saves_from_other_versions_are_refused() {
+        let (state, world) = round();
+        let mut save = SaveGame::capture(
+            &state, &world,
+        );
+        save.version = VERSION + 1;
+        assert!(
+            matches!(
+                SaveGame::from_json(&save.to_json().unwrap()),
+                Err(SaveError::Version(v)) if v == VERSION + 1
+            )
+        );
+        assert!(
+            matches!(
+                SaveGame::from_json(
+                    &format!(
+                    "{{\"version\": {}, \"players\": 3}}",
+                    VERSION
+                )
+                ),
+                Err(SaveError::Json(_))
+            )
+        );
+        assert!(
+            matches!(
+                SaveGame::from_json("not a save"),
+                Err(SaveError::Json(_))
+            )
+        );
+    }
+
+    #[test]
+    fn saves_with_strange_sprites_change_nothing() {
+        let (state, world) = round();
+        let mut save = SaveGame::capture(
+            &state, &world,
+        );
+        save.sprites[0].label = "spaceship".to_string();
+
+        let mut resumed = GameState::default();
+        resumed.players[0]
+            .score
+            .value = 3;
+        assert!(
+            matches!(
+                save.clone().restore(&mut resumed),
+                Err(SaveError::UnknownSprite(label)) if label == "spaceship"
+            )
+        );
+        assert_eq!(
+            resumed.players[0]
+                .score
+                .value,
+            3
+        );
+
+        save.sprites
+            .remove(0);
+        assert!(
+            matches!(
+                save.clone().restore(&mut resumed),
+                Err(SaveError::MissingSprite("player1"))
+            )
+        );
+
+        let mut last = save.clone();
+        last.sprites[0].label = format!("feris{}", u32::MAX);
+        assert!(
+            matches!(
+                last.restore(&mut resumed),
+                Err(SaveError::EntityNumber(label))
+                    if label == format!("feris{}", u32::MAX)
+            )
+        );
+        save.next
+            .insert(
+                "feris".to_string(),
+                u32::MAX,
+            );
+        assert!(
+            matches!(
+                save.restore(&mut resumed),
+                Err(SaveError::EntityNumber(name)) if name == "feris"
+            )
+        );
+    }
+}
@@ -1,11 +1,61 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use locale::Locale;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Seconds after collecting a feris in which the next one extends the
+/// streak.
+pub const COMBO_SECONDS: f32 = 2.0;
+/// Feris in a row needed for each step up in the multiplier.
+const COMBO_STEP: u32 = 3;
+/// Highest the multiplier goes.
+const MAX_MULTIPLIER: u32 = 5;
+
+/// Whose score a [`Score`] is, which decides how it is labelled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Owner {
+    /// The only player's.
+    #[default]
+    Solo,
+    /// One of several players', by index.
+    Player(usize),
+    /// The best score so far.
+    High,
+}
+
+impl Owner {
+    /// What goes before the player's texts, e.g. "P1 ", or nothing
+    /// on your own.
+    pub fn prefix(self, locale: &Locale) -> String {
+        match self {
+            Owner::Player(i) => locale.format(
+                "player",
+                &[(
+                    "number",
+                    &(i + 1),
+                )],
+            ),
+            Owner::Solo | Owner::High => String::new(),
+        }
+    }
+}
 
 /// Score struct for keeping track of both current and high score.
 pub struct Score {
     /// The score value
     pub value: u32,
-    /// The prefix. Default is "". For high score it is "High ".
-    pub prefix: String,
+    pub owner: Owner,
+    /// Feris collected in a row, each within [`COMBO_SECONDS`] of the
+    /// one before.
+    pub streak: u32,
+    /// Seconds left to extend the streak.
+    combo_left: f32,
 }
 impl PartialEq for Score {
     fn eq(&self, other: &Self) -> bool {
@@ -32,30 +82,676 @@ impl std::ops::AddAssign<u32> for Score {
         self.value += rhs;
     }
 }
+/// Subtracting never takes a score below zero.
+impl std::ops::SubAssign<u32> for Score {
+    fn sub_assign(&mut self, rhs: u32) {
+        self.value = self
+            .value
+            .saturating_sub(rhs);
+    }
+}
 impl Default for Score {
     fn default() -> Self {
-        Self::new(0, "")
+        Self::new(
+            0,
+            Owner::Solo,
+        )
     }
 }
 
 impl Score {
-    pub fn new<S: Into<String>>(value: u32, prefix: S) -> Self {
+    pub fn new(value: u32, owner: Owner) -> Self {
         Self {
             value,
-            prefix: prefix.into(),
+            owner,
+            streak: 0,
+            combo_left: 0.0,
+        }
+    }
+
+    /// Points the next feris is worth: one more for every
+    /// [`COMBO_STEP`] in the current streak, up to [`MAX_MULTIPLIER`].
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.streak / COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    /// Score a collected feris at the current multiplier and extend
+    /// the streak. Returns the points scored.
+    pub fn collect(&mut self) -> u32 {
+        let points = self.multiplier();
+        self.value += points;
+        self.streak += 1;
+        self.combo_left = COMBO_SECONDS;
+        points
+    }
+
+    /// Run the streak's timer down by `delta` seconds. Returns whether
+    /// the streak ran out.
+    pub fn tick(&mut self, delta: f32) -> bool {
+        if self.streak == 0 {
+            return false;
+        }
+        self.combo_left -= delta;
+        if self.combo_left > 0.0 {
+            return false;
+        }
+        self.streak = 0;
+        true
+    }
+
+    /// Back to zero with no streak, keeping the owner.
+    pub fn reset(&mut self) {
+        *self = Self::new(
+            0, self.owner,
+        );
+    }
+
+    /// Load a score value saved by [`Score::save`]. A missing or
+    /// corrupt file falls back to zero with a logged warning.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        owner: Owner,
+    ) -> Self {
+        let path = path.as_ref();
+        let value = match fs::read_to_string(path) {
+            Ok(contents) => match contents
+                .trim()
+                .parse()
+            {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!(
+                        "Corrupt score file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    0
+                }
+            },
+            Err(e) => {
+                warn!(
+                    "Could not read score file {}: {}",
+                    path.display(),
+                    e
+                );
+                0
+            }
+        };
+        Self::new(
+            value, owner,
+        )
+    }
+
+    /// Save the score value to `path`, creating its parent
+    /// directories if needed.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            self.value
+                .to_string(),
+        )
+    }
+
+    /// The score as shown in `locale`. `{}` shows just the score,
+    /// while `{:#}` adds the multiplier and streak.
+    pub fn display<'a>(
+        &'a self,
+        locale: &'a Locale,
+    ) -> ScoreText<'a> {
+        ScoreText {
+            score: self,
+            locale,
+        }
+    }
+}
+
+/// A [`Score`] in a [`Locale`], from [`Score::display`].
+pub struct ScoreText<'a> {
+    score: &'a Score,
+    locale: &'a Locale,
+}
+
+impl fmt::Display for ScoreText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let score = self.score;
+        let text = match score.owner {
+            Owner::High => self
+                .locale
+                .format(
+                    "high_score",
+                    &[(
+                        "score",
+                        &score.value,
+                    )],
+                ),
+            owner => self
+                .locale
+                .format(
+                    "score",
+                    &[
+                        (
+                            "player",
+                            &owner.prefix(self.locale),
+                        ),
+                        (
+                            "score",
+                            &score.value,
+                        ),
+                    ],
+                ),
+        };
+        f.write_str(&text)?;
+        if f.alternate() {
+            f.write_str(
+                &self
+                    .locale
+                    .plural(
+                        "streak",
+                        score.streak,
+                        &[(
+                            "multiplier",
+                            &score.multiplier(),
+                        )],
+                    ),
+            )?;
         }
+        Ok(())
     }
 }
 
-impl std::fmt::Display for Score {
-    fn fmt(
+/// One finished run on the [`Leaderboard`].
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Entry {
+    /// The player's initials.
+    pub name: String,
+    pub score: u32,
+    /// Day the run finished, as `YYYY-MM-DD`.
+    pub date: String,
+    /// How long the run lasted in seconds.
+    pub seconds: f32,
+}
+
+/// The best runs so far, highest score first.
+#[derive(
+    Clone, Debug, PartialEq, Serialize, Deserialize,
+)]
+pub struct Leaderboard {
+    entries: Vec<Entry>,
+    /// Most entries kept.
+    #[serde(skip)]
+    capacity: usize,
+}
+
+impl Leaderboard {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Load a leaderboard saved by [`Leaderboard::save`], keeping
+    /// the best `capacity` entries. A missing file is an empty
+    /// leaderboard, and a corrupt one is reported with a warning.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+    ) -> Self {
+        let path = path.as_ref();
+        let leaderboard = match fs::read_to_string(path) {
+            Ok(contents) => {
+                match serde_json::from_str::<Self>(
+                    &contents,
+                ) {
+                    Ok(leaderboard) => leaderboard,
+                    Err(e) => {
+                        warn!(
+                            "Corrupt leaderboard {}: {}",
+                            path.display(),
+                            e
+                        );
+                        Self::new(capacity)
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Self::new(capacity)
+            }
+            Err(e) => {
+                warn!(
+                    "Could not read leaderboard {}: {}",
+                    path.display(),
+                    e
+                );
+                Self::new(capacity)
+            }
+        };
+        leaderboard.with_capacity(capacity)
+    }
+
+    /// This leaderboard keeping its best `capacity` entries.
+    pub fn with_capacity(
+        mut self,
+        capacity: usize,
+    ) -> Self {
+        self.capacity = capacity;
+        self.entries
+            .sort_by(
+                |a, b| {
+                    b.score
+                        .cmp(&a.score)
+                },
+            );
+        self.entries
+            .truncate(capacity);
+        self
+    }
+
+    /// Save the leaderboard to `path` as JSON, creating its parent
+    /// directories if needed.
+    pub fn save<P: AsRef<Path>>(
         &self,
-        f: &mut std::fmt::Formatter,
-    ) -> std::fmt::Result {
-        write!(
-            f,
-            "{}Score: {}",
-            self.prefix, self.value
+        path: P,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            serde_json::to_string_pretty(self)?,
         )
     }
+
+    /// Whether a run scoring `score` would make the leaderboard.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self
+                .entries
+                .len()
+                < self.capacity
+                || self
+                    .entries
+                    .last()
+                    .is_some_and(|last| score > last.score))
+    }
+
+    /// Add `entry` below any equal scores, returning its index, or
+    /// `None` if it didn't make the leaderboard.
+    pub fn insert(
+        &mut self,
+        entry: Entry,
+    ) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let index = self
+            .entries
+            .partition_point(
+                |other| other.score >= entry.score,
+            );
+        self.entries
+            .insert(
+                index, entry,
+            );
+        self.entries
+            .truncate(self.capacity);
+        Some(index)
+    }
+}
+
+impl Leaderboard {
+    /// The leaderboard as shown in `locale`, which only has to say
+    /// when there are no scores yet.
+    pub fn text(&self, locale: &Locale) -> String {
+        if self
+            .entries
+            .is_empty()
+        {
+            locale.text("no_scores")
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Leaderboard {
+    /// One line per entry, e.g. ` 1. ABC    42  2024-05-01  1:30`.
+    /// Nothing when empty.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+        {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let seconds = entry
+                .seconds
+                .round() as u32;
+            write!(
+                f,
+                "{:>2}. {:<3} {:>5}  {}  {}:{:02}",
+                i + 1,
+                entry.name,
+                entry.score,
+                entry.date,
+                seconds / 60,
+                seconds % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() / 86_400)
+        .unwrap_or(0);
+    date(days)
+}
+
+/// `YYYY-MM-DD` of the day `days` after 1970-01-01, in the proleptic
+/// Gregorian calendar.
+fn date(days: u64) -> String {
+    // Count from 0000-03-01 so leap days fall at the end of a year
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460
+        + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4
+            - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year =
+        year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}",
+        year, month, day
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::locales;
+
+    #[test]
+    fn streaks_raise_the_multiplier() {
+        let mut score = Score::default();
+        let points: Vec<u32> = (0..8)
+            .map(|_| {
+                score.tick(1.0);
+                score.collect()
+            })
+            .collect();
+        assert_eq!(
+            points,
+            [1, 1, 1, 2, 2, 2, 3, 3]
+        );
+        let locale = locales::select(None);
+        assert_eq!(
+            format!(
+                "{:#}",
+                score.display(&locale)
+            ),
+            "Score: 15 (x3, 8 in a row)"
+        );
+        assert_eq!(
+            score
+                .display(&locale)
+                .to_string(),
+            "Score: 15"
+        );
+    }
+
+    #[test]
+    fn streaks_time_out() {
+        let mut score = Score::new(
+            0,
+            Owner::Player(0),
+        );
+        for _ in 0..5 {
+            score.collect();
+        }
+        assert!(!score.tick(COMBO_SECONDS / 2.0));
+        assert!(score.tick(COMBO_SECONDS / 2.0));
+        assert_eq!(
+            score.multiplier(),
+            1
+        );
+        assert!(!score.tick(1.0));
+        score.reset();
+        let locale = locales::select(None);
+        assert_eq!(
+            format!(
+                "{:#}",
+                score.display(&locale)
+            ),
+            "P1 Score: 0 (x1, 0 in a row)"
+        );
+        let german = locales::select(Some(
+            "de",
+        ));
+        assert_eq!(
+            format!(
+                "{:#}",
+                score.display(&german)
+            ),
+            "S1 Punkte: 0 (x1, 0 in Folge)"
+        );
+        assert_eq!(
+            Score::new(
+                7,
+                Owner::High
+            )
+            .display(&german)
+            .to_string(),
+            "Rekord: 7"
+        );
+    }
+
+    fn entry(name: &str, score: u32) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+            date: "2024-05-01".to_string(),
+            seconds: 90.0,
+        }
+    }
+
+    #[test]
+    fn leaderboard_keeps_the_best_runs() {
+        let mut leaderboard = Leaderboard::new(3);
+        assert!(!leaderboard.qualifies(0));
+        assert_eq!(
+            leaderboard.insert(entry("AAA", 10)),
+            Some(0)
+        );
+        assert_eq!(
+            leaderboard.insert(entry("BBB", 30)),
+            Some(0)
+        );
+        assert_eq!(
+            leaderboard.insert(entry("CCC", 10)),
+            Some(2)
+        );
+        assert!(!leaderboard.qualifies(10));
+        assert_eq!(
+            leaderboard.insert(entry("DDD", 20)),
+            Some(1)
+        );
+        let names: Vec<_> = leaderboard
+            .entries
+            .iter()
+            .map(
+                |entry| {
+                    entry
+                        .name
+                        .as_str()
+                },
+            )
+            .collect();
+        assert_eq!(
+            names,
+            ["BBB", "DDD", "AAA"]
+        );
+    }
+
+    #[test]
+    fn leaderboard_round_trips_through_json() {
+        let mut leaderboard = Leaderboard::new(10);
+        leaderboard.insert(
+            entry(
+                "ABC", 42,
+            ),
+        );
+        let json =
+            serde_json::to_string(&leaderboard).unwrap();
+        let mut loaded: Leaderboard =
+            serde_json::from_str(&json).unwrap();
+        loaded.capacity = 10;
+        assert_eq!(
+            loaded,
+            leaderboard
+        );
+        assert_eq!(
+            loaded.to_string(),
+            " 1. ABC    42  2024-05-01  1:30"
+        );
+    }
+
+    #[test]
+    fn score_files_fall_back_to_zero() {
+        let dir = std::env::temp_dir().join(
+            format!(
+                "score-test-{}",
+                std::process::id()
+            ),
+        );
+        let path = dir.join("high_score");
+        assert_eq!(
+            Score::load(&path, Owner::High).value,
+            0
+        );
+
+        Score::new(
+            42,
+            Owner::High,
+        )
+        .save(&path)
+        .unwrap();
+        let loaded = Score::load(&path, Owner::High);
+        assert_eq!(
+            loaded.value,
+            42
+        );
+        assert_eq!(
+            loaded.owner,
+            Owner::High
+        );
+
+        fs::write(
+            &path, "lots",
+        )
+        .unwrap();
+        assert_eq!(
+            Score::load(&path, Owner::High).value,
+            0
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaderboard_files_fall_back_to_empty() {
+        let dir = std::env::temp_dir().join(
+            format!(
+                "leaderboard-test-{}",
+                std::process::id()
+            ),
+        );
+        let path = dir.join("leaderboard.json");
+        let mut leaderboard = Leaderboard::new(3);
+        for (name, score) in [
+            (
+                "AAA", 5,
+            ),
+            (
+                "BBB", 9,
+            ),
+            (
+                "CCC", 7,
+            ),
+        ] {
+            leaderboard.insert(
+                entry(
+                    name, score,
+                ),
+            );
+        }
+        leaderboard
+            .save(&path)
+            .unwrap();
+        assert_eq!(
+            Leaderboard::load(&path, 3),
+            leaderboard
+        );
+        // A smaller board keeps only the best
+        assert_eq!(
+            Leaderboard::load(&path, 1).to_string(),
+            " 1. BBB     9  2024-05-01  1:30"
+        );
+
+        fs::write(
+            &path,
+            "{ not json",
+        )
+        .unwrap();
+        assert_eq!(
+            Leaderboard::load(&path, 3),
+            Leaderboard::new(3)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            Leaderboard::load(&path, 3),
+            Leaderboard::new(3)
+        );
+    }
+
+    #[test]
+    fn dates_count_from_the_epoch() {
+        assert_eq!(
+            date(0),
+            "1970-01-01"
+        );
+        assert_eq!(
+            date(11_016),
+            "2000-02-29"
+        );
+        assert_eq!(
+            date(20_744),
+            "2026-10-18"
+        );
+    }
 }
@@ -0,0 +1,85 @@
+//! The screens the game moves between.
+//!
+//! - [`Screen::Title`]: shows the leaderboard, and start begins a
+//!   round.
+//! - [`Screen::Playing`]: pause pauses, reset starts the round over
+//!   and running out of time ends it.
+//! - [`Screen::Paused`]: pause resumes and reset starts the round over.
+//! - [`Screen::GameOver`]: start begins a new round. After a run
+//!   that made the leaderboard, start confirms the initials typed in
+//!   instead and moves on to the title screen.
+//!
+//! Quitting works from every screen.
+
+use key_bindings::Action;
+use rusty_engine::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Screen {
+    #[default]
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+impl Screen {
+    /// Message key of the large text across the middle of the window.
+    pub fn message(self) -> Option<&'static str> {
+        match self {
+            Screen::Title => Some("title"),
+            Screen::Playing => None,
+            Screen::Paused => Some("paused"),
+            Screen::GameOver => Some("game_over"),
+        }
+    }
+
+    /// The action that leaves this screen, and the message key of the
+    /// hint under the message saying so.
+    pub fn hint(
+        self,
+    ) -> Option<(
+        Action,
+        &'static str,
+    )> {
+        match self {
+            Screen::Title => Some((
+                Action::Start,
+                "hint_start",
+            )),
+            Screen::Playing => None,
+            Screen::Paused => Some((
+                Action::Pause,
+                "hint_resume",
+            )),
+            Screen::GameOver => Some((
+                Action::Start,
+                "hint_play_again",
+            )),
+        }
+    }
+
+    /// Music playing on this screen, and its volume.
+    pub fn music(
+        self,
+    ) -> Option<(
+        MusicPreset,
+        f32,
+    )> {
+        match self {
+            Screen::Title => Some((
+                MusicPreset::WhimsicalPopsicle,
+                0.1,
+            )),
+            Screen::Playing => Some((
+                MusicPreset::Classy8Bit,
+                0.1,
+            )),
+            Screen::Paused => None,
+            Screen::GameOver => Some((
+                MusicPreset::MysteriousMagic,
+                0.1,
+            )),
+        }
+    }
+}
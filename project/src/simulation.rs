@@ -0,0 +1,3270 @@
+//! The gameplay rules, independent of rusty_engine's `Engine`.
+//!
+//! [`step`] advances a [`GameState`] and a [`World`] by one frame of
+//! [`Input`] and returns the [`Effect`]s the engine has to apply. No
+//! window or GPU is needed, so whole games can be simulated in tests.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use entities::Registry;
+use key_bindings::{Action, KeyBindings};
+use locale::Locale;
+use log::warn;
+use rand::prelude::*;
+use rusty_engine::prelude::*;
+
+use crate::collision;
+use crate::difficulty::Difficulty;
+use crate::entity::{self, Entity, EntityId};
+use crate::events::{EventBus, GameEvent};
+use crate::locales;
+use crate::physics::Physics;
+use crate::powerup::{self, PowerUp};
+use crate::replay::{Playback, Recorder};
+use crate::save::SaveGame;
+use crate::score::{
+    self, Entry, Leaderboard, Owner, Score,
+};
+use crate::screen::Screen;
+use crate::spatial::BroadPhase;
+use crate::steering::{self, Motion};
+use crate::storage;
+
+/// File in the user data directory holding the high score.
+const HIGH_SCORE_FILE: &str = "high_score";
+/// File in the user data directory holding the leaderboard.
+const LEADERBOARD_FILE: &str = "leaderboard.json";
+/// File in the user data directory holding a round quit partway.
+const SAVE_FILE: &str = "save.json";
+/// Number of runs kept on the leaderboard.
+pub const LEADERBOARD_SIZE: usize = 10;
+/// Most letters in a leaderboard name.
+const INITIALS: usize = 3;
+
+/// Most cars that can be on the field at once.
+pub const MAX_PLAYERS: usize = 2;
+/// Sprite label of each player's car.
+pub const PLAYER_LABELS: [&str; MAX_PLAYERS] =
+    ["player1", "player2"];
+/// Label of the text showing each player's score.
+pub const SCORE_LABELS: [&str; MAX_PLAYERS] =
+    ["score1", "score2"];
+/// Label of the text counting down each player's power-ups.
+pub const POWERUP_LABELS: [&str; MAX_PLAYERS] =
+    ["powerups1", "powerups2"];
+/// Image used for every feris sprite.
+pub const FERIS_IMAGE: &str = "cuddly-feris.png";
+/// Scale of the player sprite.
+pub const PLAYER_SCALE: f32 = 1.3;
+/// Collision radius of the player sprite at scale 1.0, approximating
+/// the racing car's collider.
+const PLAYER_RADIUS: f32 = 25.0;
+/// Collision radius of a feris sprite at scale 1.0, approximating the
+/// feris image.
+const FERIS_RADIUS: f32 = 100.0;
+/// How long a new level is announced for.
+const BANNER_SECONDS: f32 = 2.0;
+/// Seconds a feris stays on screen if nobody collects it.
+pub const FERIS_LIFETIME: f32 = 6.0;
+/// Seconds at the end of a feris' lifetime spent shrinking away.
+const FERIS_SHRINK_SECONDS: f32 = 0.5;
+/// Points lost for every feris that disappears uncollected.
+const MISSED_PENALTY: u32 = 2;
+/// Length of a round in seconds.
+pub const ROUND_SECONDS: f32 = 90.0;
+
+#[derive(Resource)]
+pub struct GameState {
+    /// Which screen is showing, deciding what input does.
+    pub screen: Screen,
+    /// Language of every text shown.
+    pub locale: Locale,
+    pub high_score: Score,
+    /// Where the high score is persisted. `None` disables saving.
+    pub high_score_path: Option<PathBuf>,
+    pub leaderboard: Leaderboard,
+    /// Where the leaderboard is persisted. `None` disables saving.
+    pub leaderboard_path: Option<PathBuf>,
+    /// Where a round quit partway is saved. `None` disables saving.
+    pub save_path: Option<PathBuf>,
+    /// Initials being typed in on the game over screen.
+    pub name_entry: Option<NameEntry>,
+    /// Everyone on the field, in the order of [`PLAYER_LABELS`].
+    pub players: Vec<Player>,
+    /// How the car accelerates and stays on screen.
+    pub physics: Physics,
+    /// Every feris and power-up on the field, labelling their sprites.
+    pub entities: Registry<Entity>,
+    pub spawn_timer: Timer,
+    /// Counts down the time left in the round.
+    pub round_timer: Timer,
+    /// How long each feris on screen has left, by sprite label.
+    pub lifetimes: BTreeMap<String, Lifetime>,
+    /// How each feris on screen moves by itself, by sprite label.
+    pub motions: BTreeMap<String, Motion>,
+    /// Counts down to the next appearance of each kind of power-up.
+    pub powerup_timers: BTreeMap<PowerUp, Timer>,
+    /// How long each power-up on the field has left, by sprite label.
+    pub pickups: BTreeMap<String, Timer>,
+    /// Levels the game goes through as the score rises.
+    pub difficulty: Difficulty,
+    /// Index of the current level in `difficulty`.
+    pub level: usize,
+    /// Hides the level banner when it finishes.
+    pub banner_timer: Option<Timer>,
+    /// Seed `rng` was created from, so a run can be replayed.
+    pub seed: u64,
+    /// Source of every random decision in the rules.
+    pub rng: StdRng,
+    /// Pairs of bodies that were touching after the last frame.
+    pub contacts: HashSet<CollisionPair>,
+    /// How the pairs of bodies worth checking for contact are found.
+    pub broad_phase: BroadPhase,
+    /// Records every frame's input when set.
+    pub recorder: Option<Recorder<BufWriter<File>>>,
+    /// Replaces the keyboard and mouse when set.
+    pub playback: Option<Playback>,
+    /// What happened during the last frame.
+    pub events: Vec<GameEvent>,
+    /// Who gets told about `events`.
+    pub bus: EventBus,
+    /// Hides the toast when it finishes.
+    pub toast_timer: Option<Timer>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        let difficulty = Difficulty::default();
+        Self {
+            screen: Screen::default(),
+            locale: locales::select(None),
+            high_score: Score::new(
+                0,
+                Owner::High,
+            ),
+            high_score_path: None,
+            leaderboard: Leaderboard::new(LEADERBOARD_SIZE),
+            leaderboard_path: None,
+            save_path: None,
+            name_entry: None,
+            players: vec![Player::new(
+                Score::default(),
+                KeyBindings::default(),
+            )],
+            physics: Physics::default(),
+            entities: Registry::new(),
+            lifetimes: BTreeMap::new(),
+            motions: BTreeMap::new(),
+            powerup_timers: PowerUp::ALL
+                .into_iter()
+                .map(
+                    |power_up| {
+                        (
+                            power_up,
+                            Timer::from_seconds(
+                                power_up.interval(),
+                                TimerMode::Repeating,
+                            ),
+                        )
+                    },
+                )
+                .collect(),
+            pickups: BTreeMap::new(),
+            spawn_timer: Timer::from_seconds(
+                difficulty
+                    .level(0)
+                    .spawn_interval,
+                TimerMode::Repeating,
+            ),
+            round_timer: Timer::from_seconds(
+                ROUND_SECONDS,
+                TimerMode::Once,
+            ),
+            difficulty,
+            level: 0,
+            banner_timer: None,
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
+            contacts: HashSet::new(),
+            broad_phase: BroadPhase::default(),
+            recorder: None,
+            playback: None,
+            events: Vec::new(),
+            bus: EventBus::default(),
+            toast_timer: None,
+        }
+    }
+}
+
+impl GameState {
+    /// Game state with the high score and leaderboard loaded from the
+    /// user data directory, spawning feris from `seed`. A round saved
+    /// there is not resumed until it is passed to
+    /// [`SaveGame::restore`].
+    pub fn new(seed: u64) -> Self {
+        let high_score_path =
+            storage::data_file(HIGH_SCORE_FILE);
+        let high_score = match &high_score_path {
+            Some(path) => Score::load(
+                path,
+                Owner::High,
+            ),
+            None => {
+                warn!("No user data directory, the high score will not be saved");
+                Score::new(
+                    0,
+                    Owner::High,
+                )
+            }
+        };
+        let leaderboard_path =
+            storage::data_file(LEADERBOARD_FILE);
+        let leaderboard = match &leaderboard_path {
+            Some(path) => Leaderboard::load(
+                path,
+                LEADERBOARD_SIZE,
+            ),
+            None => Leaderboard::new(LEADERBOARD_SIZE),
+        };
+        Self {
+            high_score,
+            high_score_path,
+            leaderboard,
+            leaderboard_path,
+            save_path: storage::data_file(SAVE_FILE),
+            ..Self::with_seed(seed)
+        }
+    }
+
+    /// Game state that spawns feris from `seed`, without touching the
+    /// disk.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Replace the levels, staying at the level the current score
+    /// reaches.
+    pub fn set_difficulty(
+        &mut self,
+        difficulty: Difficulty,
+    ) {
+        self.difficulty = difficulty;
+        self.level = self
+            .difficulty
+            .level_for(self.top_score());
+        self.spawn_timer
+            .set_duration(self.spawn_interval());
+    }
+
+    /// Put a car on the field for each of `key_bindings`, with scores
+    /// labelled "P1", "P2" and so on when there is more than one.
+    pub fn set_players(
+        &mut self,
+        key_bindings: Vec<KeyBindings>,
+    ) {
+        assert!(
+            (1..=MAX_PLAYERS).contains(&key_bindings.len()),
+            "{} players",
+            key_bindings.len()
+        );
+        let several = key_bindings.len() > 1;
+        self.players = key_bindings
+            .into_iter()
+            .enumerate()
+            .map(
+                |(i, key_bindings)| {
+                    let owner = if several {
+                        Owner::Player(i)
+                    } else {
+                        Owner::Solo
+                    };
+                    Player::new(
+                        Score::new(
+                            0, owner,
+                        ),
+                        key_bindings,
+                    )
+                },
+            )
+            .collect();
+    }
+
+    /// Score of whoever is in the lead, which sets the level.
+    pub fn top_score(&self) -> u32 {
+        self.players
+            .iter()
+            .map(
+                |player| {
+                    player
+                        .score
+                        .value
+                },
+            )
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn spawn_interval(&self) -> Duration {
+        Duration::from_secs_f32(
+            self.difficulty
+                .level(self.level)
+                .spawn_interval,
+        )
+    }
+
+    pub fn save_high_score(&self) {
+        if let Some(path) = &self.high_score_path {
+            if let Err(e) = self
+                .high_score
+                .save(path)
+            {
+                warn!(
+                    "Could not save high score to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    pub fn save_leaderboard(&self) {
+        if let Some(path) = &self.leaderboard_path {
+            if let Err(e) = self
+                .leaderboard
+                .save(path)
+            {
+                warn!(
+                    "Could not save leaderboard to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Save the round being played on `world`, to resume on the next
+    /// launch.
+    pub fn save_game(&self, world: &World) {
+        if let Some(path) = &self.save_path {
+            if let Err(e) = SaveGame::capture(
+                self, world,
+            )
+            .save(path)
+            {
+                warn!(
+                    "Could not save the game to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Large text across the middle of the window for the current
+    /// screen.
+    pub fn message(&self) -> String {
+        self.screen
+            .message()
+            .map(
+                |key| {
+                    self.locale
+                        .text(key)
+                },
+            )
+            .unwrap_or_default()
+    }
+
+    /// "Press <key> to <verb>" for leaving the current screen, or the
+    /// initials typed so far.
+    pub fn hint(&self) -> String {
+        let start = self
+            .key_name(Action::Start)
+            .unwrap_or("?");
+        if let Some(entry) = &self.name_entry {
+            return self
+                .locale
+                .format(
+                    "new_best",
+                    &[
+                        (
+                            "player",
+                            &self.players[entry.players[0]]
+                                .score
+                                .owner
+                                .prefix(&self.locale),
+                        ),
+                        (
+                            "key", &start,
+                        ),
+                        (
+                            "name",
+                            &entry.name,
+                        ),
+                    ],
+                );
+        }
+        let Some((action, message)) = self
+            .screen
+            .hint()
+        else {
+            return String::new();
+        };
+        match self.key_name(action) {
+            Some(key) => self
+                .locale
+                .format(
+                    message,
+                    &[(
+                        "key", &key,
+                    )],
+                ),
+            None => String::new(),
+        }
+    }
+
+    /// Name of the first key for `action`.
+    fn key_name(
+        &self,
+        action: Action,
+    ) -> Option<&'static str> {
+        self.players[0]
+            .key_bindings
+            .keys(action)
+            .first()
+            .and_then(|&key| key_bindings::key_name(key))
+    }
+}
+
+/// One of the cars on the field.
+pub struct Player {
+    pub score: Score,
+    pub velocity: Vec2,
+    /// Keys steering this car. The first player's also hold the keys
+    /// for starting, pausing, resetting and quitting.
+    pub key_bindings: KeyBindings,
+    /// Time left on each power-up in effect.
+    pub powerups: BTreeMap<PowerUp, Timer>,
+}
+
+impl Player {
+    pub fn new(
+        score: Score,
+        key_bindings: KeyBindings,
+    ) -> Self {
+        Self {
+            score,
+            velocity: Vec2::new(
+                1.0, 0.0,
+            ),
+            key_bindings,
+            powerups: BTreeMap::new(),
+        }
+    }
+
+    pub fn has(&self, power_up: PowerUp) -> bool {
+        self.powerups
+            .contains_key(&power_up)
+    }
+
+    /// Scale of the car, smaller while shrunk.
+    pub fn scale(&self) -> f32 {
+        if self.has(PowerUp::Shrink) {
+            PLAYER_SCALE * powerup::SHRINK_SCALE
+        } else {
+            PLAYER_SCALE
+        }
+    }
+
+    /// The power-ups in effect with their seconds left, e.g.
+    /// "Magnet 4s  x2 7s".
+    pub fn powerups_text(&self, locale: &Locale) -> String {
+        if self
+            .powerups
+            .is_empty()
+        {
+            return String::new();
+        }
+        let countdowns: Vec<String> = self
+            .powerups
+            .iter()
+            .map(
+                |(power_up, timer)| {
+                    locale.format(
+                        "power_up",
+                        &[
+                            (
+                                "name",
+                                &power_up.name(locale),
+                            ),
+                            (
+                                "seconds",
+                                &timer
+                                    .remaining_secs()
+                                    .ceil(),
+                            ),
+                        ],
+                    )
+                },
+            )
+            .collect();
+        format!(
+            "{}{}",
+            self.score
+                .owner
+                .prefix(locale),
+            countdowns.join("  ")
+        )
+    }
+}
+
+/// Initials being typed for the leaderboard after a round.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameEntry {
+    /// Players whose runs made the leaderboard, best first. The first
+    /// is typing.
+    pub players: Vec<usize>,
+    pub name: String,
+    /// How long the round lasted.
+    pub seconds: f32,
+}
+
+/// Countdown until an uncollected feris disappears.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lifetime {
+    pub timer: Timer,
+    /// Scale the feris spawned with, before it starts shrinking.
+    pub scale: f32,
+}
+
+/// Size of the window until the engine reports otherwise, which is
+/// bevy's default window size.
+pub const DEFAULT_WINDOW: Vec2 = Vec2::new(
+    1280.0, 720.0,
+);
+
+/// Which way one player is steering.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Steering {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+/// Snapshot of the players' input for one frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Input {
+    /// Steering of each player, by index.
+    pub steering: [Steering; MAX_PLAYERS],
+    /// Quit was pressed this frame.
+    pub quit: bool,
+    /// Reset was pressed this frame.
+    pub reset: bool,
+    /// Start was pressed this frame.
+    pub start: bool,
+    /// Pause was pressed this frame.
+    pub pause: bool,
+    /// Letter typed this frame.
+    pub letter: Option<char>,
+    /// The last letter typed was erased this frame.
+    pub erase: bool,
+    /// Where the left mouse button was clicked this frame.
+    pub click: Option<Vec2>,
+    /// Seconds since the previous frame.
+    pub delta: f32,
+    /// Size of the window, which the car has to stay within.
+    pub window: Vec2,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self {
+            steering: Default::default(),
+            quit: false,
+            reset: false,
+            start: false,
+            pause: false,
+            letter: None,
+            erase: false,
+            click: None,
+            delta: 0.0,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// Position and size of a sprite, as far as the rules care.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Body {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: f32,
+    /// Collision radius, already multiplied by `scale`.
+    pub radius: f32,
+}
+
+impl Body {
+    pub fn player(translation: Vec2) -> Self {
+        Self::car(
+            translation,
+            PLAYER_SCALE,
+        )
+    }
+
+    pub fn car(translation: Vec2, scale: f32) -> Self {
+        Self {
+            translation,
+            rotation: 0.0,
+            scale,
+            radius: PLAYER_RADIUS * scale,
+        }
+    }
+
+    pub fn powerup(translation: Vec2) -> Self {
+        Self {
+            translation,
+            rotation: 0.0,
+            scale: powerup::SCALE,
+            radius: powerup::RADIUS * powerup::SCALE,
+        }
+    }
+
+    pub fn feris(translation: Vec2, scale: f32) -> Self {
+        Self {
+            translation,
+            rotation: 0.0,
+            scale,
+            radius: FERIS_RADIUS * scale,
+        }
+    }
+}
+
+/// Where player `index` of `players` starts a round, side by side
+/// around the middle of the window.
+pub fn start_position(
+    index: usize,
+    players: usize,
+) -> Vec2 {
+    const SPACING: f32 = 200.0;
+    Vec2::new(
+        (index as f32 - (players - 1) as f32 / 2.0)
+            * SPACING,
+        0.0,
+    )
+}
+
+/// Every sprite the rules act on, keyed by sprite label.
+#[derive(Clone, Debug, PartialEq)]
+pub struct World {
+    /// The cars, in the order of [`PLAYER_LABELS`].
+    pub players: Vec<Body>,
+    /// Ordered by label so every run visits them in the same order.
+    pub feris: BTreeMap<String, Body>,
+    /// Power-ups lying on the field, by label.
+    pub powerups: BTreeMap<String, Body>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl World {
+    /// A field with `players` cars in their starting positions.
+    pub fn new(players: usize) -> Self {
+        Self {
+            players: (0..players)
+                .map(|i| {
+                    Body::player(
+                        start_position(
+                            i, players,
+                        ),
+                    )
+                })
+                .collect(),
+            feris: BTreeMap::new(),
+            powerups: BTreeMap::new(),
+        }
+    }
+
+    /// All bodies with their labels, the players first and the
+    /// power-ups last.
+    pub fn bodies(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            &str,
+            &Body,
+        ),
+    > {
+        PLAYER_LABELS
+            .into_iter()
+            .zip(&self.players)
+            .chain(
+                self.feris
+                    .iter()
+                    .chain(&self.powerups)
+                    .map(
+                        |(label, body)| {
+                            (
+                                label.as_str(),
+                                body,
+                            )
+                        },
+                    ),
+            )
+    }
+
+    /// Index of the player whose car is labelled `label`.
+    pub fn player_index(
+        &self,
+        label: &str,
+    ) -> Option<usize> {
+        PLAYER_LABELS[..self
+            .players
+            .len()]
+            .iter()
+            .position(|player| *player == label)
+    }
+
+    pub fn contains(&self, label: &str) -> bool {
+        self.player_index(label)
+            .is_some()
+            || self
+                .feris
+                .contains_key(label)
+            || self
+                .powerups
+                .contains_key(label)
+    }
+}
+
+/// Something the engine has to do as a result of a frame.
+#[derive(Clone, Debug)]
+pub enum Effect {
+    PlaySfx(
+        SfxPreset,
+        f32,
+    ),
+    PlayMusic(
+        MusicPreset,
+        f32,
+    ),
+    StopMusic,
+    SetText {
+        label: &'static str,
+        value: String,
+    },
+    /// Show a message on the HUD for a few seconds.
+    Toast(String),
+    SpawnSprite {
+        label: String,
+        filepath: &'static str,
+        translation: Vec2,
+        scale: f32,
+    },
+    ScaleSprite {
+        label: String,
+        scale: f32,
+    },
+    MoveSprite {
+        label: String,
+        translation: Vec2,
+    },
+    RemoveSprite(String),
+    SaveHighScore,
+    SaveLeaderboard,
+    /// Save the round in progress to resume later.
+    SaveGame,
+    Exit,
+}
+
+/// Advance the game by one frame.
+///
+/// `world` is updated in place to match the returned effects, so a
+/// headless caller can keep feeding it back in.
+pub fn step(
+    state: &mut GameState,
+    input: &Input,
+    world: &mut World,
+) -> Vec<Effect> {
+    let mut effects = Vec::new();
+    state
+        .events
+        .clear();
+    if input.quit {
+        state
+            .events
+            .push(GameEvent::Quit);
+        if matches!(
+            state.screen,
+            Screen::Playing | Screen::Paused
+        ) {
+            effects.push(Effect::SaveGame);
+        }
+        effects.push(Effect::Exit);
+    }
+    match state.screen {
+        Screen::GameOver
+            if state
+                .name_entry
+                .is_some() =>
+        {
+            enter_name(
+                state,
+                input,
+                &mut effects,
+            );
+        }
+        Screen::Title | Screen::GameOver => {
+            if input.start {
+                start_round(
+                    state,
+                    world,
+                    &mut effects,
+                );
+            }
+        }
+        Screen::Playing => play(
+            state,
+            input,
+            world,
+            &mut effects,
+        ),
+        Screen::Paused => {
+            if input.reset {
+                state
+                    .events
+                    .push(GameEvent::Reset);
+                start_round(
+                    state,
+                    world,
+                    &mut effects,
+                );
+            } else if input.pause {
+                change_screen(
+                    state,
+                    Screen::Playing,
+                    &mut effects,
+                );
+            }
+        }
+    }
+    effects
+}
+
+/// Show a round just restored from a save on `world`, paused until
+/// the player carries on.
+pub fn resume(
+    state: &mut GameState,
+    world: &World,
+) -> Vec<Effect> {
+    let mut effects = Vec::new();
+    for (label, body) in &world.feris {
+        effects.push(
+            Effect::SpawnSprite {
+                label: label.clone(),
+                filepath: FERIS_IMAGE,
+                translation: body.translation,
+                scale: body.scale,
+            },
+        );
+    }
+    for (label, body) in &world.powerups {
+        if let Some(Entity::PowerUp(power_up)) =
+            entity::kind_of(label)
+        {
+            effects.push(
+                Effect::SpawnSprite {
+                    label: label.clone(),
+                    filepath: power_up.image(),
+                    translation: body.translation,
+                    scale: body.scale,
+                },
+            );
+        }
+    }
+    push_scores(
+        state,
+        &mut effects,
+    );
+    for (player, label) in state
+        .players
+        .iter()
+        .zip(POWERUP_LABELS)
+    {
+        effects.push(
+            Effect::SetText {
+                label,
+                value: player.powerups_text(&state.locale),
+            },
+        );
+    }
+    effects.push(
+        Effect::SetText {
+            label: "clock",
+            value: clock_text(state),
+        },
+    );
+    change_screen(
+        state,
+        Screen::Paused,
+        &mut effects,
+    );
+    effects
+}
+
+/// Show `screen`'s texts and music.
+fn change_screen(
+    state: &mut GameState,
+    screen: Screen,
+    effects: &mut Vec<Effect>,
+) {
+    let music = track(state.screen);
+    match (
+        state.screen,
+        screen,
+    ) {
+        (Screen::Playing, Screen::Paused) => state
+            .events
+            .push(GameEvent::Paused),
+        (Screen::Paused, Screen::Playing) => state
+            .events
+            .push(GameEvent::Resumed),
+        _ => {}
+    }
+    state.screen = screen;
+    effects.push(
+        Effect::SetText {
+            label: "message",
+            value: state.message(),
+        },
+    );
+    effects.push(
+        Effect::SetText {
+            label: "hint",
+            value: state.hint(),
+        },
+    );
+    effects.push(
+        Effect::SetText {
+            label: "leaderboard",
+            value: if screen == Screen::Title {
+                state
+                    .leaderboard
+                    .text(&state.locale)
+            } else {
+                String::new()
+            },
+        },
+    );
+    if track(screen) != music {
+        effects.push(
+            match screen.music() {
+                Some((preset, volume)) => {
+                    Effect::PlayMusic(
+                        preset, volume,
+                    )
+                }
+                None => Effect::StopMusic,
+            },
+        );
+    }
+}
+
+/// File and volume of `screen`'s music. `MusicPreset` can't be compared,
+/// so tracks are told apart by the file they play.
+fn track(screen: Screen) -> Option<(String, f32)> {
+    screen
+        .music()
+        .map(|(preset, volume)| {
+            (
+                String::from(preset),
+                volume,
+            )
+        })
+}
+
+/// One frame on the playing screen.
+fn play(
+    state: &mut GameState,
+    input: &Input,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    if input.reset {
+        state
+            .events
+            .push(GameEvent::Reset);
+        start_round(
+            state, world, effects,
+        );
+        return;
+    }
+    if input.pause {
+        change_screen(
+            state,
+            Screen::Paused,
+            effects,
+        );
+        return;
+    }
+
+    for event in collision::detect(
+        world,
+        &mut state.contacts,
+        &mut state.broad_phase,
+    ) {
+        if event
+            .state
+            .is_begin()
+        {
+            touch(
+                state, event.pair, world, effects,
+            );
+        }
+    }
+    age_feris(
+        state,
+        input.delta,
+        world,
+        effects,
+    );
+    for (index, (player, label)) in state
+        .players
+        .iter_mut()
+        .zip(SCORE_LABELS)
+        .enumerate()
+    {
+        if player
+            .score
+            .tick(input.delta)
+        {
+            state
+                .events
+                .push(
+                    GameEvent::StreakEnded {
+                        player: index,
+                    },
+                );
+            effects.push(
+                Effect::SetText {
+                    label,
+                    value: format!(
+                        "{:#}",
+                        player
+                            .score
+                            .display(&state.locale)
+                    ),
+                },
+            );
+        }
+    }
+    update_level(
+        state, effects,
+    );
+    tick_powerups(
+        state,
+        input.delta,
+        world,
+        effects,
+    );
+
+    move_players(
+        state, input, world,
+    );
+    move_feris(
+        state, input, world, effects,
+    );
+    pull_feris(
+        state,
+        input.delta,
+        world,
+        effects,
+    );
+
+    if let Some(location) = input.click {
+        spawn_feris(
+            state, location, true, world, effects,
+        );
+    }
+
+    if state
+        .spawn_timer
+        .tick(Duration::from_secs_f32(input.delta))
+        .just_finished()
+    {
+        let level = state
+            .difficulty
+            .level(state.level);
+        let room = level
+            .max_feris
+            .map_or(
+                u32::MAX,
+                |max| {
+                    max.saturating_sub(
+                        world
+                            .feris
+                            .len()
+                            as u32,
+                    )
+                },
+            );
+        let count = level
+            .feris_per_spawn
+            .min(room);
+        for _ in 0..count {
+            let location = random_location(state);
+            spawn_feris(
+                state, location, false, world, effects,
+            );
+        }
+        if count > 0 {
+            effects.push(
+                Effect::PlaySfx(
+                    SfxPreset::Minimize1,
+                    0.5,
+                ),
+            );
+        }
+    }
+
+    if let Some(banner_timer) = &mut state.banner_timer {
+        if banner_timer
+            .tick(Duration::from_secs_f32(input.delta))
+            .finished()
+        {
+            state.banner_timer = None;
+            effects.push(
+                Effect::SetText {
+                    label: "banner",
+                    value: String::new(),
+                },
+            );
+        }
+    }
+
+    let shown = clock_text(state);
+    state
+        .round_timer
+        .tick(Duration::from_secs_f32(input.delta));
+    if state
+        .round_timer
+        .finished()
+    {
+        end_round(
+            state, effects,
+        );
+    } else if clock_text(state) != shown {
+        effects.push(
+            Effect::SetText {
+                label: "clock",
+                value: clock_text(state),
+            },
+        );
+    }
+}
+
+/// Time left in the round, in whole seconds.
+fn clock_text(state: &GameState) -> String {
+    state
+        .locale
+        .plural(
+            "clock",
+            state
+                .round_timer
+                .remaining_secs()
+                .ceil() as u32,
+            &[],
+        )
+}
+
+/// Move to the level the score has reached, announcing it if the game
+/// got harder.
+fn update_level(
+    state: &mut GameState,
+    effects: &mut Vec<Effect>,
+) {
+    let level = state
+        .difficulty
+        .level_for(state.top_score());
+    if level == state.level {
+        return;
+    }
+    let harder = level > state.level;
+    state.level = level;
+    let spawn_interval = state.spawn_interval();
+    state
+        .spawn_timer
+        .set_duration(spawn_interval);
+    if harder {
+        effects.push(
+            Effect::SetText {
+                label: "banner",
+                value: state
+                    .locale
+                    .format(
+                        "level",
+                        &[(
+                            "level",
+                            &(level + 1),
+                        )],
+                    ),
+            },
+        );
+        effects.push(
+            Effect::PlaySfx(
+                SfxPreset::Confirmation1,
+                0.6,
+            ),
+        );
+        state.banner_timer = Some(
+            Timer::from_seconds(
+                BANNER_SECONDS,
+                TimerMode::Once,
+            ),
+        );
+    }
+}
+
+/// Give the player in `pair` the feris or power-up they touched.
+/// Pairs of cars, or of things lying on the field, are left alone.
+fn touch(
+    state: &mut GameState,
+    pair: CollisionPair,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let mut player = None;
+    let mut other = None;
+    for label in pair {
+        match world.player_index(&label) {
+            Some(index) => player = Some(index),
+            None => other = Some(label),
+        }
+    }
+    let (Some(index), Some(id)) = (
+        player,
+        other
+            .and_then(|label| EntityId::from_label(&label)),
+    ) else {
+        return;
+    };
+    match id.kind() {
+        Entity::PowerUp(power_up) => pick_up(
+            state, index, power_up, id, world, effects,
+        ),
+        Entity::Feris => collect_feris(
+            state, index, id, world, effects,
+        ),
+    }
+}
+
+/// Credit player `index` with the feris labelled `label`.
+fn collect_feris(
+    state: &mut GameState,
+    index: usize,
+    feris: EntityId,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let label = feris.label();
+    // Somebody else may have got it first this frame
+    if world
+        .feris
+        .remove(&label)
+        .is_none()
+    {
+        return;
+    }
+    state
+        .entities
+        .remove(feris);
+    let age = state
+        .lifetimes
+        .remove(&label)
+        .map_or(
+            0.0,
+            |lifetime| {
+                lifetime
+                    .timer
+                    .elapsed_secs()
+            },
+        );
+    state
+        .motions
+        .remove(&label);
+    effects.push(Effect::RemoveSprite(label));
+
+    let player = &mut state.players[index];
+    let mut points = player
+        .score
+        .collect();
+    if player.has(PowerUp::Doubler) {
+        player.score += points;
+        points *= 2;
+    }
+    state
+        .events
+        .push(
+            GameEvent::FerisCollected {
+                player: index,
+                points,
+                score: player
+                    .score
+                    .value,
+                seconds: state
+                    .round_timer
+                    .elapsed_secs(),
+                age,
+            },
+        );
+    effects.push(
+        Effect::SetText {
+            label: SCORE_LABELS[index],
+            value: format!(
+                "{:#}",
+                player
+                    .score
+                    .display(&state.locale)
+            ),
+        },
+    );
+    if player
+        .score
+        .value
+        > state
+            .high_score
+            .value
+    {
+        state
+            .high_score
+            .value = player
+            .score
+            .value;
+        state
+            .events
+            .push(
+                GameEvent::HighScore {
+                    score: state
+                        .high_score
+                        .value,
+                },
+            );
+        effects.push(
+            Effect::SetText {
+                label: "high_score",
+                value: state
+                    .high_score
+                    .display(&state.locale)
+                    .to_string(),
+            },
+        );
+        effects.push(Effect::SaveHighScore);
+    }
+    effects.push(
+        Effect::PlaySfx(
+            SfxPreset::Jingle1,
+            0.5,
+        ),
+    );
+}
+
+/// Start `power_up`, labelled `label` on the field, for player
+/// `index`. Picking up one that is already in effect starts it over.
+fn pick_up(
+    state: &mut GameState,
+    index: usize,
+    power_up: PowerUp,
+    id: EntityId,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let label = id.label();
+    // Somebody else may have got it first this frame
+    if world
+        .powerups
+        .remove(&label)
+        .is_none()
+    {
+        return;
+    }
+    state
+        .entities
+        .remove(id);
+    state
+        .pickups
+        .remove(&label);
+    effects.push(Effect::RemoveSprite(label));
+    state
+        .events
+        .push(
+            GameEvent::PowerUpCollected {
+                player: index,
+                power_up,
+            },
+        );
+
+    let player = &mut state.players[index];
+    player
+        .powerups
+        .insert(
+            power_up,
+            Timer::from_seconds(
+                power_up.seconds(),
+                TimerMode::Once,
+            ),
+        );
+    effects.push(
+        Effect::SetText {
+            label: POWERUP_LABELS[index],
+            value: player.powerups_text(&state.locale),
+        },
+    );
+    fit_cars(
+        state, world, effects,
+    );
+    effects.push(
+        Effect::PlaySfx(
+            SfxPreset::Forcefield1,
+            0.5,
+        ),
+    );
+}
+
+/// Count down the power-ups in effect and on the field, and put out
+/// each kind that is due.
+fn tick_powerups(
+    state: &mut GameState,
+    delta: f32,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let delta = Duration::from_secs_f32(delta);
+    for (player, label) in state
+        .players
+        .iter_mut()
+        .zip(POWERUP_LABELS)
+    {
+        let shown = player.powerups_text(&state.locale);
+        for timer in player
+            .powerups
+            .values_mut()
+        {
+            timer.tick(delta);
+        }
+        player
+            .powerups
+            .retain(|_, timer| !timer.finished());
+        let text = player.powerups_text(&state.locale);
+        if text != shown {
+            effects.push(
+                Effect::SetText { label, value: text },
+            );
+        }
+    }
+    fit_cars(
+        state, world, effects,
+    );
+
+    let mut expired = Vec::new();
+    for (label, timer) in &mut state.pickups {
+        if timer
+            .tick(delta)
+            .finished()
+        {
+            expired.push(label.clone());
+        }
+    }
+    for label in expired {
+        forget(
+            state, &label,
+        );
+        state
+            .pickups
+            .remove(&label);
+        world
+            .powerups
+            .remove(&label);
+        effects.push(Effect::RemoveSprite(label));
+    }
+
+    let mut due = Vec::new();
+    for (&power_up, timer) in &mut state.powerup_timers {
+        if timer
+            .tick(delta)
+            .just_finished()
+            && state.level >= power_up.first_level()
+        {
+            due.push(power_up);
+        }
+    }
+    for power_up in due {
+        // One of each kind on the field at a time
+        if world
+            .powerups
+            .keys()
+            .any(
+                |label| {
+                    entity::kind_of(label)
+                        == Some(Entity::PowerUp(power_up))
+                },
+            )
+        {
+            continue;
+        }
+        let location = random_location(state);
+        spawn_powerup(
+            state, power_up, location, world, effects,
+        );
+    }
+}
+
+/// Size every car for the power-ups in effect.
+fn fit_cars(
+    state: &GameState,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    for ((player, body), label) in state
+        .players
+        .iter()
+        .zip(&mut world.players)
+        .zip(PLAYER_LABELS)
+    {
+        let scale = player.scale();
+        if body.scale != scale {
+            *body = Body {
+                rotation: body.rotation,
+                ..Body::car(
+                    body.translation,
+                    scale,
+                )
+            };
+            effects.push(
+                Effect::ScaleSprite {
+                    label: label.to_string(),
+                    scale,
+                },
+            );
+        }
+    }
+}
+
+/// Draw the feris near a car with a magnet towards it.
+fn pull_feris(
+    state: &GameState,
+    delta: f32,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    for (player, car) in state
+        .players
+        .iter()
+        .zip(&world.players)
+    {
+        if !player.has(PowerUp::Magnet) {
+            continue;
+        }
+        for (label, body) in &mut world.feris {
+            let offset = car.translation - body.translation;
+            let distance = offset.length();
+            if distance == 0.0
+                || distance > powerup::MAGNET_RADIUS
+            {
+                continue;
+            }
+            body.translation += offset / distance
+                * (powerup::MAGNET_SPEED * delta)
+                    .min(distance);
+            effects.push(
+                Effect::MoveSprite {
+                    label: label.clone(),
+                    translation: body.translation,
+                },
+            );
+        }
+    }
+}
+
+/// Move each feris the way its behavior wants, keeping it on screen.
+fn move_feris(
+    state: &mut GameState,
+    input: &Input,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    // Where everyone was at the start of the frame, so the order the
+    // feris move in makes no difference
+    let neighbours: Vec<steering::Neighbour> = world
+        .feris
+        .iter()
+        .map(
+            |(label, body)| {
+                (
+                    body.translation,
+                    state
+                        .motions
+                        .get(label)
+                        .map_or(
+                            Vec2::ZERO,
+                            |motion| motion.velocity,
+                        ),
+                )
+            },
+        )
+        .collect();
+    let cars: Vec<Vec2> = world
+        .players
+        .iter()
+        .map(|car| car.translation)
+        .collect();
+    for (label, motion) in &mut state.motions {
+        let Some(body) = world
+            .feris
+            .get_mut(label)
+        else {
+            continue;
+        };
+        if !motion
+            .behavior
+            .moves()
+            && motion.velocity == Vec2::ZERO
+        {
+            continue;
+        }
+        let velocity = motion.steer(
+            body.translation,
+            &neighbours,
+            &cars,
+            &mut state.rng,
+            input.delta,
+        );
+        body.translation += velocity * input.delta;
+        steering::confine(
+            &mut body.translation,
+            &mut motion.velocity,
+            body.radius,
+            input.window,
+        );
+        effects.push(
+            Effect::MoveSprite {
+                label: label.clone(),
+                translation: body.translation,
+            },
+        );
+    }
+}
+
+/// Steer every car, keeping it on screen.
+fn move_players(
+    state: &mut GameState,
+    input: &Input,
+    world: &mut World,
+) {
+    for ((player, body), steering) in state
+        .players
+        .iter_mut()
+        .zip(&mut world.players)
+        .zip(&input.steering)
+    {
+        let mut direction = Vec2::new(
+            0.0, 0.0,
+        );
+        if steering.up {
+            direction.y += 1.0;
+        }
+        if steering.down {
+            direction.y -= 1.0;
+        }
+        if steering.left {
+            direction.x -= 1.0;
+        }
+        if steering.right {
+            direction.x += 1.0;
+        }
+        let physics = if player.has(PowerUp::Speed) {
+            Physics {
+                acceleration: state
+                    .physics
+                    .acceleration
+                    * powerup::SPEED_BOOST,
+                max_speed: state
+                    .physics
+                    .max_speed
+                    * powerup::SPEED_BOOST,
+                ..state
+                    .physics
+                    .clone()
+            }
+        } else {
+            state
+                .physics
+                .clone()
+        };
+        player.velocity = physics.accelerate(
+            player.velocity,
+            direction,
+            input.delta,
+        );
+        body.translation += player.velocity * input.delta;
+        physics.confine(
+            &mut body.translation,
+            &mut player.velocity,
+            body.radius,
+            input.window,
+        );
+        body.rotation = heading(player.velocity);
+    }
+}
+
+/// Rotation of a car moving at `velocity`.
+fn heading(velocity: Vec2) -> f32 {
+    if velocity
+        .abs()
+        .max_element()
+        == 0.0
+    {
+        0.0
+    } else {
+        f32::atan(velocity.y / velocity.x)
+            + ((velocity.x < 0.0) as u32 as f32)
+                * std::f32::consts::PI
+    }
+}
+
+/// Take the feris or power-up labelled `label` out of the registry.
+fn forget(state: &mut GameState, label: &str) {
+    if let Some(id) = state
+        .entities
+        .get(label)
+    {
+        state
+            .entities
+            .remove(id);
+    }
+}
+
+/// Shrink feris near the end of their lifetime and remove the expired
+/// ones, costing every player points for each.
+fn age_feris(
+    state: &mut GameState,
+    delta: f32,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let mut expired = Vec::new();
+    for (label, lifetime) in &mut state.lifetimes {
+        lifetime
+            .timer
+            .tick(Duration::from_secs_f32(delta));
+        let remaining = lifetime
+            .timer
+            .remaining_secs();
+        if lifetime
+            .timer
+            .finished()
+        {
+            expired.push(label.clone());
+        } else if remaining < FERIS_SHRINK_SECONDS {
+            let scale = lifetime.scale * remaining
+                / FERIS_SHRINK_SECONDS;
+            if let Some(body) = world
+                .feris
+                .get_mut(label)
+            {
+                *body = Body::feris(
+                    body.translation,
+                    scale,
+                );
+            }
+            effects.push(
+                Effect::ScaleSprite {
+                    label: label.clone(),
+                    scale,
+                },
+            );
+        }
+    }
+    if expired.is_empty() {
+        return;
+    }
+    for label in expired {
+        forget(
+            state, &label,
+        );
+        state
+            .lifetimes
+            .remove(&label);
+        state
+            .motions
+            .remove(&label);
+        world
+            .feris
+            .remove(&label);
+        effects.push(Effect::RemoveSprite(label));
+        state
+            .events
+            .push(GameEvent::FerisMissed);
+        for player in &mut state.players {
+            player.score -= MISSED_PENALTY;
+        }
+    }
+    push_scores(
+        state, effects,
+    );
+    effects.push(
+        Effect::PlaySfx(
+            SfxPreset::Impact1,
+            0.4,
+        ),
+    );
+}
+
+/// Somewhere inside the default window, picked by `state`'s random
+/// number generator.
+fn random_location(state: &mut GameState) -> Vec2 {
+    Vec2::new(
+        state
+            .rng
+            .gen_range(-550.0..550.0),
+        state
+            .rng
+            .gen_range(-305.0..305.0),
+    )
+}
+
+/// Put a feris at `location`, where a click put it if `clicked`.
+fn spawn_feris(
+    state: &mut GameState,
+    location: Vec2,
+    clicked: bool,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let level = state
+        .difficulty
+        .level(state.level);
+    let (scale, behavior) = (
+        level.feris_scale,
+        level.behavior,
+    );
+    let label = state
+        .entities
+        .spawn(Entity::Feris)
+        .label();
+    state
+        .events
+        .push(
+            GameEvent::FerisSpawned {
+                clicked,
+                position: location,
+            },
+        );
+    world
+        .feris
+        .insert(
+            label.clone(),
+            Body::feris(
+                location, scale,
+            ),
+        );
+    state
+        .lifetimes
+        .insert(
+            label.clone(),
+            Lifetime {
+                timer: Timer::from_seconds(
+                    FERIS_LIFETIME,
+                    TimerMode::Once,
+                ),
+                scale,
+            },
+        );
+    state
+        .motions
+        .insert(
+            label.clone(),
+            Motion::new(behavior),
+        );
+    effects.push(
+        Effect::SpawnSprite {
+            label,
+            filepath: FERIS_IMAGE,
+            translation: location,
+            scale,
+        },
+    );
+}
+
+fn spawn_powerup(
+    state: &mut GameState,
+    power_up: PowerUp,
+    location: Vec2,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    let label = state
+        .entities
+        .spawn(Entity::PowerUp(power_up))
+        .label();
+    world
+        .powerups
+        .insert(
+            label.clone(),
+            Body::powerup(location),
+        );
+    state
+        .pickups
+        .insert(
+            label.clone(),
+            Timer::from_seconds(
+                powerup::LIFETIME,
+                TimerMode::Once,
+            ),
+        );
+    effects.push(
+        Effect::SpawnSprite {
+            label,
+            filepath: power_up.image(),
+            translation: location,
+            scale: powerup::SCALE,
+        },
+    );
+}
+
+/// Clear the field and start a new round on the playing screen.
+fn start_round(
+    state: &mut GameState,
+    world: &mut World,
+    effects: &mut Vec<Effect>,
+) {
+    state
+        .events
+        .push(GameEvent::RoundStarted);
+    let players = state
+        .players
+        .len();
+    for (i, (player, body)) in state
+        .players
+        .iter_mut()
+        .zip(&mut world.players)
+        .enumerate()
+    {
+        player
+            .score
+            .reset();
+        player.velocity = Vec2::new(
+            0.0, 0.0,
+        );
+        player
+            .powerups
+            .clear();
+        body.translation = start_position(
+            i, players,
+        );
+        effects.push(
+            Effect::SetText {
+                label: POWERUP_LABELS[i],
+                value: String::new(),
+            },
+        );
+    }
+    fit_cars(
+        state, world, effects,
+    );
+    state
+        .entities
+        .clear();
+    for label in std::mem::take(&mut world.feris)
+        .into_keys()
+        .chain(
+            std::mem::take(&mut world.powerups).into_keys(),
+        )
+    {
+        effects.push(Effect::RemoveSprite(label));
+    }
+    state
+        .lifetimes
+        .clear();
+    state
+        .motions
+        .clear();
+    state
+        .pickups
+        .clear();
+    for timer in state
+        .powerup_timers
+        .values_mut()
+    {
+        timer.reset();
+    }
+    update_level(
+        state, effects,
+    );
+    state
+        .spawn_timer
+        .reset();
+    state
+        .round_timer
+        .reset();
+    state.banner_timer = None;
+    effects.push(
+        Effect::SetText {
+            label: "banner",
+            value: String::new(),
+        },
+    );
+    push_scores(
+        state, effects,
+    );
+    effects.push(
+        Effect::SetText {
+            label: "clock",
+            value: clock_text(state),
+        },
+    );
+    change_screen(
+        state,
+        Screen::Playing,
+        effects,
+    );
+}
+
+/// Time is up: show the final score on the game over screen.
+fn end_round(
+    state: &mut GameState,
+    effects: &mut Vec<Effect>,
+) {
+    let mut players: Vec<usize> = (0..state
+        .players
+        .len())
+        .filter(
+            |&i| {
+                state
+                    .leaderboard
+                    .qualifies(
+                        state.players[i]
+                            .score
+                            .value,
+                    )
+            },
+        )
+        .collect();
+    players.sort_by_key(
+        |&i| {
+            std::cmp::Reverse(
+                state.players[i]
+                    .score
+                    .value,
+            )
+        },
+    );
+    if !players.is_empty() {
+        state.name_entry = Some(
+            NameEntry {
+                players,
+                name: String::new(),
+                seconds: state
+                    .round_timer
+                    .elapsed_secs(),
+            },
+        );
+    }
+    state
+        .events
+        .push(
+            GameEvent::RoundEnded {
+                scores: state
+                    .players
+                    .iter()
+                    .map(
+                        |player| {
+                            player
+                                .score
+                                .value
+                        },
+                    )
+                    .collect(),
+            },
+        );
+    change_screen(
+        state,
+        Screen::GameOver,
+        effects,
+    );
+    effects.push(
+        Effect::SetText {
+            label: "banner",
+            value: result(state),
+        },
+    );
+    state.banner_timer = None;
+    effects.push(Effect::SaveHighScore);
+    effects.push(
+        Effect::PlaySfx(
+            SfxPreset::Jingle3,
+            0.6,
+        ),
+    );
+}
+
+/// Type initials for the leaderboard. Once every player who made it
+/// has confirmed theirs, the title screen shows the leaderboard.
+fn enter_name(
+    state: &mut GameState,
+    input: &Input,
+    effects: &mut Vec<Effect>,
+) {
+    let Some(mut entry) = state
+        .name_entry
+        .take()
+    else {
+        return;
+    };
+    let mut changed = input
+        .letter
+        .is_some()
+        || input.erase;
+    if let Some(letter) = input
+        .letter
+        .filter(char::is_ascii_alphabetic)
+    {
+        if entry
+            .name
+            .len()
+            < INITIALS
+        {
+            entry
+                .name
+                .push(letter.to_ascii_uppercase());
+        }
+    }
+    if input.erase {
+        entry
+            .name
+            .pop();
+    }
+    if input.start
+        && !entry
+            .name
+            .is_empty()
+    {
+        let player = entry
+            .players
+            .remove(0);
+        state
+            .leaderboard
+            .insert(
+                Entry {
+                    name: std::mem::take(&mut entry.name),
+                    score: state.players[player]
+                        .score
+                        .value,
+                    date: score::today(),
+                    seconds: entry.seconds,
+                },
+            );
+        // Whoever is next may have been pushed off the board
+        entry
+            .players
+            .retain(
+                |&i| {
+                    state
+                        .leaderboard
+                        .qualifies(
+                            state.players[i]
+                                .score
+                                .value,
+                        )
+                },
+            );
+        if entry
+            .players
+            .is_empty()
+        {
+            effects.push(Effect::SaveLeaderboard);
+            change_screen(
+                state,
+                Screen::Title,
+                effects,
+            );
+            return;
+        }
+        // The next player's turn
+        changed = true;
+    }
+    state.name_entry = Some(entry);
+    if changed {
+        effects.push(
+            Effect::SetText {
+                label: "hint",
+                value: state.hint(),
+            },
+        );
+    }
+}
+
+/// Show every player's score.
+fn push_scores(
+    state: &GameState,
+    effects: &mut Vec<Effect>,
+) {
+    for (player, label) in state
+        .players
+        .iter()
+        .zip(SCORE_LABELS)
+    {
+        effects.push(
+            Effect::SetText {
+                label,
+                value: format!(
+                    "{:#}",
+                    player
+                        .score
+                        .display(&state.locale)
+                ),
+            },
+        );
+    }
+}
+
+/// How the round ended: the score on your own, or who won.
+fn result(state: &GameState) -> String {
+    if let [player] = state
+        .players
+        .as_slice()
+    {
+        return player
+            .score
+            .display(&state.locale)
+            .to_string();
+    }
+    let top = state.top_score();
+    let mut leaders = state
+        .players
+        .iter()
+        .enumerate()
+        .filter(
+            |(_, player)| {
+                player
+                    .score
+                    .value
+                    == top
+            },
+        );
+    match (
+        leaders.next(),
+        leaders.next(),
+    ) {
+        (Some((i, _)), None) => state
+            .locale
+            .format(
+                "winner",
+                &[(
+                    "number",
+                    &(i + 1),
+                )],
+            ),
+        _ => state
+            .locale
+            .text("draw"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::steering::Behavior;
+
+    const FRAME: f32 = 1.0 / 60.0;
+
+    fn frame() -> Input {
+        Input {
+            delta: FRAME,
+            ..Default::default()
+        }
+    }
+
+    /// `state` partway through a round.
+    fn playing(state: GameState) -> GameState {
+        GameState {
+            screen: Screen::Playing,
+            ..state
+        }
+    }
+
+    #[test]
+    fn touching_a_feris_collects_it() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        world
+            .feris
+            .insert(
+                "feris0".to_string(),
+                Body::feris(
+                    Vec2::ZERO,
+                    0.4,
+                ),
+            );
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert!(world
+            .feris
+            .is_empty());
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            1
+        );
+        assert_eq!(
+            state
+                .high_score
+                .value,
+            1
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::RemoveSprite(label) if label == "feris0"
+            ))
+        );
+        assert!(
+            effects
+                .iter()
+                .any(|effect| matches!(effect, Effect::SaveHighScore))
+        );
+    }
+
+    #[test]
+    fn feris_go_to_whoever_touches_them() {
+        let mut state = playing(GameState::default());
+        state.set_players(vec![
+            KeyBindings::default(),
+            KeyBindings::unbound(),
+        ]);
+        let mut world = World::new(2);
+        world
+            .feris
+            .insert(
+                "feris0".to_string(),
+                Body::feris(
+                    world.players[1].translation,
+                    0.4,
+                ),
+            );
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            0
+        );
+        assert_eq!(
+            state.players[1]
+                .score
+                .value,
+            1
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::SetText { label: "score2", value }
+                    if value == "P2 Score: 1 (x1, 1 in a row)"
+            ))
+        );
+        assert_eq!(
+            result(&state),
+            "P2 wins!"
+        );
+    }
+
+    #[test]
+    fn frames_record_what_happened() {
+        let mut state = GameState::default();
+        let mut world = World::default();
+        step(
+            &mut state,
+            &Input {
+                start: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            state.events,
+            [GameEvent::RoundStarted]
+        );
+        step(
+            &mut state,
+            &Input {
+                click: Some(Vec2::ZERO),
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            state.events,
+            [GameEvent::FerisSpawned {
+                clicked: true,
+                position: Vec2::ZERO,
+            }]
+        );
+        step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert!(
+            matches!(
+                state.events[..],
+                [
+                    GameEvent::FerisCollected {
+                        player: 0,
+                        points: 1,
+                        score: 1,
+                        ..
+                    },
+                    GameEvent::HighScore { score: 1 }
+                ]
+            )
+        );
+        step(
+            &mut state,
+            &Input {
+                pause: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            state.events,
+            [GameEvent::Paused]
+        );
+    }
+
+    #[test]
+    fn clicking_spawns_a_feris() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        let click = Vec2::new(
+            300.0, 200.0,
+        );
+        step(
+            &mut state,
+            &Input {
+                click: Some(click),
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            world.feris["feris0"].translation,
+            click
+        );
+    }
+
+    #[test]
+    fn reset_removes_every_feris() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        for _ in 0..3 {
+            step(
+                &mut state,
+                &Input {
+                    click: Some(
+                        Vec2::new(
+                            400.0, 0.0,
+                        ),
+                    ),
+                    ..frame()
+                },
+                &mut world,
+            );
+        }
+        let effects = step(
+            &mut state,
+            &Input {
+                reset: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert!(world
+            .feris
+            .is_empty());
+        assert_eq!(
+            world.players[0].translation,
+            Vec2::ZERO
+        );
+        assert_eq!(
+            effects
+                .iter()
+                .filter(
+                    |effect| matches!(
+                        effect,
+                        Effect::RemoveSprite(_)
+                    )
+                )
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn same_seed_spawns_the_same_feris() {
+        let run = |seed| {
+            let mut state =
+                playing(GameState::with_seed(seed));
+            let mut world = World::default();
+            for _ in 0..1_000 {
+                step(
+                    &mut state,
+                    &frame(),
+                    &mut world,
+                );
+            }
+            world
+        };
+        assert_eq!(
+            run(7),
+            run(7)
+        );
+        assert_ne!(
+            run(7),
+            run(8)
+        );
+    }
+
+    #[test]
+    fn reaching_a_threshold_raises_the_level() {
+        let mut state = playing(GameState::default());
+        state.players[0]
+            .score
+            .value = 9;
+        let mut world = World::default();
+        world
+            .feris
+            .insert(
+                "feris0".to_string(),
+                Body::feris(
+                    Vec2::ZERO,
+                    0.4,
+                ),
+            );
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert_eq!(
+            state.level,
+            1
+        );
+        assert_eq!(
+            state
+                .spawn_timer
+                .duration(),
+            Duration::from_secs_f32(1.6)
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::SetText { label: "banner", value }
+                    if value == "Level 2!"
+            ))
+        );
+
+        step(
+            &mut state,
+            &Input {
+                reset: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            state.level,
+            0
+        );
+    }
+
+    #[test]
+    fn spawns_hold_off_while_the_field_is_full() {
+        let mut state = playing(GameState::default());
+        let max_feris = state
+            .difficulty
+            .level(0)
+            .max_feris
+            .unwrap() as usize;
+        let mut world = World::default();
+        // Clicking isn't held back
+        for _ in 0..max_feris + 2 {
+            step(
+                &mut state,
+                &Input {
+                    click: Some(
+                        Vec2::new(
+                            400.0, 300.0,
+                        ),
+                    ),
+                    ..frame()
+                },
+                &mut world,
+            );
+        }
+        let frames = (2.5 / FRAME) as usize;
+        for _ in 0..frames {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        assert_eq!(
+            world
+                .feris
+                .len(),
+            max_feris + 2
+        );
+    }
+
+    #[test]
+    fn missed_feris_cost_points() {
+        let mut state = playing(GameState::default());
+        state.players[0]
+            .score
+            .value = 3;
+        let mut world = World::default();
+        step(
+            &mut state,
+            &Input {
+                click: Some(
+                    Vec2::new(
+                        400.0, 300.0,
+                    ),
+                ),
+                ..frame()
+            },
+            &mut world,
+        );
+        let frames = (FERIS_LIFETIME / FRAME) as usize + 1;
+        let mut shrunk = false;
+        for _ in 0..frames {
+            shrunk |= step(
+                &mut state,
+                &frame(),
+                &mut world,
+            )
+            .iter()
+            .any(
+                |effect| {
+                    matches!(
+                        effect,
+                        Effect::ScaleSprite { .. }
+                    )
+                },
+            );
+        }
+        assert!(shrunk);
+        assert!(
+            !world
+                .feris
+                .contains_key("feris0")
+        );
+        assert!(
+            !state
+                .lifetimes
+                .contains_key("feris0")
+        );
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            3 - MISSED_PENALTY
+        );
+
+        state.players[0]
+            .score
+            .value = 1;
+        step(
+            &mut state,
+            &Input {
+                click: Some(
+                    Vec2::new(
+                        400.0, 300.0,
+                    ),
+                ),
+                ..frame()
+            },
+            &mut world,
+        );
+        for _ in 0..frames {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            0
+        );
+    }
+
+    #[test]
+    fn power_ups_are_told_apart_from_feris() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        world
+            .powerups
+            .insert(
+                "doubler0".to_string(),
+                Body::powerup(Vec2::ZERO),
+            );
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert!(state.players[0].has(PowerUp::Doubler));
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            0
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::SetText { label: "powerups1", value }
+                    if value == "x2 8s"
+            ))
+        );
+
+        world
+            .feris
+            .insert(
+                "feris0".to_string(),
+                Body::feris(
+                    Vec2::ZERO,
+                    0.4,
+                ),
+            );
+        step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert_eq!(
+            state.players[0]
+                .score
+                .value,
+            2
+        );
+    }
+
+    #[test]
+    fn magnets_pull_nearby_feris_in() {
+        let mut state = playing(GameState::default());
+        state.players[0]
+            .powerups
+            .insert(
+                PowerUp::Magnet,
+                Timer::from_seconds(
+                    PowerUp::Magnet.seconds(),
+                    TimerMode::Once,
+                ),
+            );
+        let near = Vec2::new(
+            200.0, 0.0,
+        );
+        let far = Vec2::new(
+            500.0, 300.0,
+        );
+        let mut world = World::default();
+        for (label, translation) in [
+            (
+                "feris0", near,
+            ),
+            (
+                "feris1", far,
+            ),
+        ] {
+            world
+                .feris
+                .insert(
+                    label.to_string(),
+                    Body::feris(
+                        translation,
+                        0.4,
+                    ),
+                );
+        }
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        let pulled = world.feris["feris0"].translation;
+        assert!(pulled.x < near.x);
+        assert_eq!(
+            world.feris["feris1"].translation,
+            far
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::MoveSprite { label, translation }
+                    if label == "feris0" && *translation == pulled
+            ))
+        );
+    }
+
+    #[test]
+    fn feris_flee_from_cars_close_by() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        let start = Vec2::new(
+            150.0, 0.0,
+        );
+        world
+            .feris
+            .insert(
+                "feris0".to_string(),
+                Body::feris(
+                    start, 0.4,
+                ),
+            );
+        state
+            .motions
+            .insert(
+                "feris0".to_string(),
+                Motion::new(
+                    Behavior {
+                        flee_radius: 200.0,
+                        flee_speed: 200.0,
+                        ..Default::default()
+                    },
+                ),
+            );
+        let mut effects = Vec::new();
+        for _ in 0..30 {
+            effects = step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        let fled = world.feris["feris0"].translation;
+        assert!(fled.x > start.x);
+        assert!(
+            state.motions["feris0"]
+                .velocity
+                .x
+                > 0.0
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::MoveSprite { label, translation }
+                    if label == "feris0" && *translation == fled
+            ))
+        );
+    }
+
+    #[test]
+    fn shrinking_wears_off() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        world
+            .powerups
+            .insert(
+                "shrink0".to_string(),
+                Body::powerup(Vec2::ZERO),
+            );
+        let effects = step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        let shrunk = PLAYER_SCALE * powerup::SHRINK_SCALE;
+        assert_eq!(
+            world.players[0].scale,
+            shrunk
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::ScaleSprite { label, scale }
+                    if label == "player1" && *scale == shrunk
+            ))
+        );
+
+        let frames = (PowerUp::Shrink.seconds() / FRAME)
+            as usize
+            + 1;
+        for _ in 0..frames {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        assert!(!state.players[0].has(PowerUp::Shrink));
+        assert_eq!(
+            world.players[0].scale,
+            PLAYER_SCALE
+        );
+        assert_eq!(
+            state.players[0].powerups_text(&state.locale),
+            ""
+        );
+    }
+
+    #[test]
+    fn power_ups_turn_up_on_their_own_schedule() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        let frames = (PowerUp::Speed.interval() / FRAME)
+            as usize
+            + 1;
+        for _ in 0..frames {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        let kinds: Vec<Entity> = world
+            .powerups
+            .keys()
+            .filter_map(|label| entity::kind_of(label))
+            .collect();
+        assert_eq!(
+            kinds,
+            [Entity::PowerUp(PowerUp::Speed)]
+        );
+
+        let frames =
+            (powerup::LIFETIME / FRAME) as usize + 1;
+        for _ in 0..frames {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        assert!(
+            !world
+                .powerups
+                .contains_key("speed0")
+        );
+        assert!(
+            !state
+                .pickups
+                .contains_key("speed0")
+        );
+    }
+
+    #[test]
+    fn screens_follow_the_input() {
+        let mut state = GameState::default();
+        let mut world = World::default();
+        let mut press = |input: Input| {
+            step(
+                &mut state,
+                &Input {
+                    delta: FRAME,
+                    ..input
+                },
+                &mut world,
+            );
+            state.screen
+        };
+        // The title ignores everything but start
+        assert_eq!(
+            press(
+                Input {
+                    pause: true,
+                    click: Some(Vec2::ZERO),
+                    ..frame()
+                }
+            ),
+            Screen::Title
+        );
+        assert_eq!(
+            press(
+                Input {
+                    start: true,
+                    ..frame()
+                }
+            ),
+            Screen::Playing
+        );
+        assert_eq!(
+            press(
+                Input {
+                    pause: true,
+                    ..frame()
+                }
+            ),
+            Screen::Paused
+        );
+        assert_eq!(
+            press(
+                Input {
+                    click: Some(Vec2::ZERO),
+                    ..frame()
+                }
+            ),
+            Screen::Paused
+        );
+        assert_eq!(
+            press(
+                Input {
+                    pause: true,
+                    ..frame()
+                }
+            ),
+            Screen::Playing
+        );
+        assert_eq!(
+            state
+                .entities
+                .next(Entity::Feris),
+            0
+        );
+    }
+
+    #[test]
+    fn pausing_stops_the_clock() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        step(
+            &mut state,
+            &Input {
+                pause: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        for _ in 0..1_000 {
+            step(
+                &mut state,
+                &frame(),
+                &mut world,
+            );
+        }
+        assert_eq!(
+            state
+                .round_timer
+                .elapsed_secs(),
+            0.0
+        );
+        assert!(world
+            .feris
+            .is_empty());
+    }
+
+    #[test]
+    fn runs_that_make_the_leaderboard_are_named() {
+        let mut state = playing(GameState::default());
+        state.players[0]
+            .score
+            .value = 5;
+        let mut world = World::default();
+        state
+            .round_timer
+            .tick(
+                Duration::from_secs_f32(
+                    ROUND_SECONDS - FRAME / 2.0,
+                ),
+            );
+        step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        assert_eq!(
+            state.screen,
+            Screen::GameOver
+        );
+        assert!(state
+            .name_entry
+            .is_some());
+
+        let typed = |letter| Input {
+            letter: Some(letter),
+            ..frame()
+        };
+        for input in [
+            typed('a'),
+            typed('b'),
+            Input {
+                erase: true,
+                ..frame()
+            },
+            typed('c'),
+            typed('1'),
+            typed('d'),
+            typed('e'),
+        ] {
+            step(
+                &mut state, &input, &mut world,
+            );
+        }
+        let effects = step(
+            &mut state,
+            &Input {
+                start: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert!(
+            effects
+                .iter()
+                .any(|effect| matches!(effect, Effect::SaveLeaderboard))
+        );
+        assert_eq!(
+            state.screen,
+            Screen::Title
+        );
+        let table = state
+            .leaderboard
+            .to_string();
+        assert!(table.starts_with(" 1. ACD     5  "));
+        assert!(table.ends_with("  1:30"));
+    }
+
+    #[test]
+    fn the_next_player_is_asked_for_a_name() {
+        let mut state = playing(GameState::default());
+        state.set_players(vec![
+            KeyBindings::default();
+            2
+        ]);
+        state.players[0]
+            .score
+            .value = 5;
+        state.players[1]
+            .score
+            .value = 3;
+        let mut world = World::new(2);
+        state
+            .round_timer
+            .tick(
+                Duration::from_secs_f32(
+                    ROUND_SECONDS - FRAME / 2.0,
+                ),
+            );
+        step(
+            &mut state,
+            &frame(),
+            &mut world,
+        );
+        for letter in ['a', 'b'] {
+            step(
+                &mut state,
+                &Input {
+                    letter: Some(letter),
+                    ..frame()
+                },
+                &mut world,
+            );
+        }
+        let effects = step(
+            &mut state,
+            &Input {
+                start: true,
+                ..frame()
+            },
+            &mut world,
+        );
+        assert_eq!(
+            state.screen,
+            Screen::GameOver
+        );
+        assert!(
+            effects.iter().any(|effect| matches!(
+                effect,
+                Effect::SetText { label: "hint", value }
+                    if value
+                        == "P2 New best! Type your initials \
+                            and press Space: "
+            ))
+        );
+    }
+
+    #[test]
+    fn thousands_of_frames_headless() {
+        let mut state = playing(GameState::default());
+        let mut world = World::default();
+        let mut effects = Vec::new();
+        for i in 0..10_000 {
+            let input = Input {
+                steering: [Steering {
+                    up: i % 300 < 150,
+                    down: i % 300 >= 150,
+                    left: i % 500 < 250,
+                    right: i % 500 >= 250,
+                }; MAX_PLAYERS],
+                ..frame()
+            };
+            effects = step(
+                &mut state, &input, &mut world,
+            );
+        }
+        // One feris every two seconds, until time runs out
+        assert_eq!(
+            state.screen,
+            Screen::GameOver
+        );
+        assert_eq!(
+            state
+                .entities
+                .next(Entity::Feris) as usize,
+            (ROUND_SECONDS / 2.0) as usize
+        );
+        // The game over screen only waits for input
+        assert!(effects.is_empty());
+        // Collected and expired feris leave no timers behind
+        assert!(world
+            .feris
+            .keys()
+            .eq(state
+                .lifetimes
+                .keys()));
+        let mut live: Vec<String> = state
+            .entities
+            .of_kind(Entity::Feris)
+            .map(EntityId::label)
+            .collect();
+        live.sort();
+        assert!(live
+            .iter()
+            .eq(world
+                .feris
+                .keys()));
+    }
+}
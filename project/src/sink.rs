@@ -0,0 +1,231 @@
+//! Gameplay events written out as telemetry, one JSON object per
+//! line, for the `telemetry` tool to summarize.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use log::warn;
+use telemetry::{Event, Record, Writer};
+
+use crate::events::{GameEvent, Subscriber};
+use crate::simulation::Effect;
+
+/// Seconds of frames summed up in each frame stats record.
+const FRAME_STATS_SECONDS: f32 = 1.0;
+
+pub struct TelemetrySink<W: Write> {
+    /// `None` once writing has failed.
+    writer: Option<Writer<W>>,
+    /// Seconds since the game started.
+    time: f32,
+    /// Frames since the last frame stats record.
+    frames: u32,
+    /// Total seconds of those frames.
+    frame_seconds: f32,
+    /// Longest of those frames.
+    max_frame: f32,
+}
+
+impl TelemetrySink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> TelemetrySink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Some(Writer::new(writer)),
+            time: 0.0,
+            frames: 0,
+            frame_seconds: 0.0,
+            max_frame: 0.0,
+        }
+    }
+
+    fn write(&mut self, event: Event) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        if let Err(e) = writer.write(
+            &Record {
+                time: self.time,
+                event,
+            },
+        ) {
+            warn!(
+                "Stopped writing telemetry: {}",
+                e
+            );
+            self.writer = None;
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(Err(e)) = self
+            .writer
+            .as_mut()
+            .map(Writer::flush)
+        {
+            warn!(
+                "Stopped writing telemetry: {}",
+                e
+            );
+            self.writer = None;
+        }
+    }
+}
+
+impl<W: Write + Send + Sync> Subscriber
+    for TelemetrySink<W>
+{
+    fn frame(&mut self, delta: f32) {
+        self.time += delta;
+        self.frames += 1;
+        self.frame_seconds += delta;
+        self.max_frame = self
+            .max_frame
+            .max(delta);
+        if self.frame_seconds < FRAME_STATS_SECONDS {
+            return;
+        }
+        self.write(
+            Event::FrameStats {
+                frames: self.frames,
+                mean_ms: self.frame_seconds * 1000.0
+                    / self.frames as f32,
+                max_ms: self.max_frame * 1000.0,
+            },
+        );
+        self.frames = 0;
+        self.frame_seconds = 0.0;
+        self.max_frame = 0.0;
+        self.flush();
+    }
+
+    fn notify(
+        &mut self,
+        event: &GameEvent,
+        _effects: &mut Vec<Effect>,
+    ) {
+        let event = match event {
+            GameEvent::RoundStarted => Event::RoundStarted,
+            GameEvent::Reset => Event::Reset,
+            GameEvent::FerisSpawned {
+                clicked,
+                position,
+            } => Event::FerisSpawned {
+                x: position.x,
+                y: position.y,
+                clicked: *clicked,
+            },
+            GameEvent::FerisCollected {
+                player,
+                points,
+                score,
+                age,
+                ..
+            } => Event::FerisCollected {
+                player: *player,
+                age: *age,
+                points: *points,
+                score: *score,
+            },
+            GameEvent::FerisMissed => Event::FerisMissed,
+            GameEvent::PowerUpCollected {
+                player,
+                power_up,
+            } => Event::PowerUpCollected {
+                player: *player,
+                power_up: power_up
+                    .label()
+                    .to_string(),
+            },
+            GameEvent::HighScore { score } => {
+                Event::HighScore { score: *score }
+            }
+            GameEvent::RoundEnded { scores } => {
+                Event::RoundEnded {
+                    scores: scores.clone(),
+                }
+            }
+            GameEvent::Quit => {
+                self.flush();
+                return;
+            }
+            GameEvent::Paused
+            | GameEvent::Resumed
+            | GameEvent::StreakEnded { .. } => return,
+        };
+        let round_ended = matches!(
+            event,
+            Event::RoundEnded { .. }
+        );
+        self.write(event);
+        if round_ended {
+            self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rusty_engine::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn events_become_telemetry_lines() {
+        let mut output = Vec::new();
+        let mut sink = TelemetrySink::new(&mut output);
+        let mut effects = Vec::new();
+        for _ in 0..70 {
+            sink.frame(1.0 / 60.0);
+        }
+        sink.notify(
+            &GameEvent::FerisSpawned {
+                clicked: false,
+                position: Vec2::new(
+                    10.0, -20.0,
+                ),
+            },
+            &mut effects,
+        );
+        sink.notify(
+            &GameEvent::Paused,
+            &mut effects,
+        );
+        sink.notify(
+            &GameEvent::RoundEnded { scores: vec![3] },
+            &mut effects,
+        );
+
+        assert!(effects.is_empty());
+        let records =
+            telemetry::read(output.as_slice()).unwrap();
+        assert_eq!(
+            records.len(),
+            3
+        );
+        assert!(
+            matches!(
+                records[0].event,
+                Event::FrameStats { .. }
+            )
+        );
+        assert_eq!(
+            records[1].event,
+            Event::FerisSpawned {
+                x: 10.0,
+                y: -20.0,
+                clicked: false,
+            }
+        );
+        assert!(
+            (records[2].time - 70.0 / 60.0).abs() < 1e-4
+        );
+    }
+}
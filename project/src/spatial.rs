@@ -0,0 +1,257 @@
+//! Finding the bodies that overlap without checking every pair.
+//!
+//! [`BroadPhase::AllPairs`] checks every pair of circles, the way
+//! rusty_engine checks every pair of collision-enabled sprites. With
+//! thousands of feris on the field that is millions of checks a frame,
+//! so [`BroadPhase::Grid`] first sorts the circles into the cells of a
+//! [`SpatialHash`] and only checks circles in neighbouring cells. Both
+//! find exactly the same pairs.
+
+use std::collections::HashMap;
+
+use rusty_engine::prelude::*;
+
+/// Narrowest a grid cell gets in pixels, however small the circles.
+pub const MIN_CELL: f32 = 64.0;
+
+/// A circle to check, as its centre and radius.
+pub type Circle = (
+    Vec2,
+    f32,
+);
+
+fn overlapping(a: Circle, b: Circle) -> bool {
+    a.0.distance_squared(b.0) < (a.1 + b.1).powi(2)
+}
+
+/// Circles indexed by the grid cell their centres are in.
+///
+/// Cells are at least as wide as the largest circle, so circles can
+/// only overlap if their cells are the same or next to each other.
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+    /// Narrowest the cells get.
+    min_cell: f32,
+    /// Width of the cells, set when rebuilding.
+    cell: f32,
+    /// Indices of the circles centred in each cell.
+    cells: HashMap<
+        (
+            i32,
+            i32,
+        ),
+        Vec<usize>,
+    >,
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(MIN_CELL)
+    }
+}
+
+impl SpatialHash {
+    pub fn new(min_cell: f32) -> Self {
+        assert!(
+            min_cell > 0.0,
+            "cell width {}",
+            min_cell
+        );
+        Self {
+            min_cell,
+            cell: min_cell,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(
+        &self,
+        point: Vec2,
+    ) -> (
+        i32,
+        i32,
+    ) {
+        (
+            (point.x / self.cell).floor() as i32,
+            (point.y / self.cell).floor() as i32,
+        )
+    }
+
+    /// Index `circles`, forgetting whatever was indexed before.
+    pub fn rebuild(&mut self, circles: &[Circle]) {
+        let largest = circles
+            .iter()
+            .map(|&(_, radius)| radius)
+            .fold(
+                0.0,
+                f32::max,
+            );
+        let cell = self
+            .min_cell
+            .max(2.0 * largest);
+        if cell != self.cell {
+            self.cell = cell;
+            self.cells
+                .clear();
+        }
+        // Keep the cells' allocations from frame to frame, but drop the
+        // ones left empty last time
+        self.cells
+            .retain(|_, indices| !indices.is_empty());
+        for indices in self
+            .cells
+            .values_mut()
+        {
+            indices.clear();
+        }
+        for (i, &(centre, _)) in circles
+            .iter()
+            .enumerate()
+        {
+            self.cells
+                .entry(self.cell_of(centre))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    /// Pass each pair of indices of circles in the same or
+    /// neighbouring cells to `visit`, once, lower index first.
+    pub fn candidates(
+        &self,
+        mut visit: impl FnMut(usize, usize),
+    ) {
+        // The cells after this one, so each neighbouring pair of cells
+        // is only visited from one of them
+        const FORWARD: [(
+            i32,
+            i32,
+        ); 4] = [(1, -1), (1, 0), (1, 1), (0, 1)];
+        let mut visit = |a: usize, b: usize| {
+            visit(
+                a.min(b),
+                a.max(b),
+            )
+        };
+        for (&(x, y), indices) in &self.cells {
+            for (n, &a) in indices
+                .iter()
+                .enumerate()
+            {
+                for &b in &indices[n + 1..] {
+                    visit(a, b);
+                }
+            }
+            for (dx, dy) in FORWARD {
+                let Some(others) = self
+                    .cells
+                    .get(&(
+                        x + dx,
+                        y + dy,
+                    ))
+                else {
+                    continue;
+                };
+                for &a in indices {
+                    for &b in others {
+                        visit(a, b);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How [`collision::detect`](crate::collision::detect) narrows down
+/// the pairs worth checking precisely.
+#[derive(Clone, Debug, Default)]
+pub enum BroadPhase {
+    /// Check every pair.
+    #[default]
+    AllPairs,
+    /// Check only pairs in neighbouring cells of the grid.
+    Grid(SpatialHash),
+}
+
+impl BroadPhase {
+    /// A grid broad phase with cells at least [`MIN_CELL`] wide.
+    pub fn grid() -> Self {
+        BroadPhase::Grid(SpatialHash::default())
+    }
+
+    /// Pairs of indices of the overlapping `circles`, lower index
+    /// first, in no particular order.
+    pub fn overlapping(
+        &mut self,
+        circles: &[Circle],
+    ) -> Vec<(
+        usize,
+        usize,
+    )> {
+        match self {
+            BroadPhase::AllPairs => (0..circles.len())
+                .flat_map(|a| {
+                    (a + 1..circles.len())
+                        .map(move |b| (a, b))
+                })
+                .filter(
+                    |&(a, b)| {
+                        overlapping(
+                            circles[a], circles[b],
+                        )
+                    },
+                )
+                .collect(),
+            BroadPhase::Grid(hash) => {
+                hash.rebuild(circles);
+                let mut pairs = Vec::new();
+                hash.candidates(
+                    |a, b| {
+                        if overlapping(
+                            circles[a], circles[b],
+                        ) {
+                            pairs.push((a, b));
+                        }
+                    },
+                );
+                pairs
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn grid_finds_the_same_pairs_as_checking_all() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut grid = BroadPhase::grid();
+        for _ in 0..20 {
+            // Circles of mixed sizes, on both sides of the origin
+            let circles: Vec<Circle> = (0..200)
+                .map(|_| {
+                    (
+                        Vec2::new(
+                            rng.gen_range(-640.0..640.0),
+                            rng.gen_range(-360.0..360.0),
+                        ),
+                        rng.gen_range(5.0..100.0),
+                    )
+                })
+                .collect();
+            let expected =
+                BroadPhase::AllPairs.overlapping(&circles);
+            assert!(!expected.is_empty());
+            let mut pairs = grid.overlapping(&circles);
+            pairs.sort();
+            assert_eq!(
+                pairs,
+                expected
+            );
+        }
+    }
+}
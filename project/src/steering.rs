@@ -0,0 +1,502 @@
+//! How feris move about by themselves.
+//!
+//! Each feris spawns with the [`Behavior`] of the current level. Every
+//! frame it picks the velocity it would like to have and turns
+//! towards it:
+//!
+//! - Wandering drifts along a heading that changes a little at random.
+//! - Fleeing runs straight away from the nearest car within reach.
+//! - Chasing heads for the nearest car within reach, unless it is
+//!   close enough to flee from.
+//! - Flocking keeps with the feris around it, without bumping into
+//!   them.
+//!
+//! A level sets any of them in the difficulty file:
+//!
+//! ```toml
+//! behavior = { wander = 60.0, flee_radius = 150.0, flee_speed = 200.0 }
+//! ```
+
+use std::f32::consts::PI;
+
+use rand::prelude::*;
+use rusty_engine::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Radians per second a wandering feris turns at most.
+pub const WANDER_TURN: f32 = 3.0;
+/// Distance within which feris flock together.
+pub const FLOCK_RADIUS: f32 = 120.0;
+/// Distance flocking feris try to keep from each other.
+pub const SEPARATION: f32 = 40.0;
+/// Fraction of the way to the velocity it wants a feris turns in a
+/// second.
+pub const STEER_RATE: f32 = 4.0;
+
+/// How a feris moves by itself. The default stands still.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    PartialEq,
+    Serialize,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct Behavior {
+    /// Speed of wandering about, in pixels per second.
+    pub wander: f32,
+    /// Distance from a car within which the feris runs away.
+    pub flee_radius: f32,
+    /// Speed of running away, in pixels per second.
+    pub flee_speed: f32,
+    /// Distance from a car within which the feris comes after it.
+    pub chase_radius: f32,
+    /// Speed of coming after a car, in pixels per second.
+    pub chase_speed: f32,
+    /// How much the feris keeps with the ones around it, from 0 to 1.
+    pub flock: f32,
+}
+
+impl Behavior {
+    /// Whether a feris with this behavior ever moves.
+    pub fn moves(&self) -> bool {
+        self.wander > 0.0
+            || (self.flee_radius > 0.0
+                && self.flee_speed > 0.0)
+            || (self.chase_radius > 0.0
+                && self.chase_speed > 0.0)
+    }
+}
+
+/// A feris's behavior and how it is moving.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Motion {
+    pub behavior: Behavior,
+    /// In pixels per second.
+    pub velocity: Vec2,
+}
+
+/// Where a feris is and how it is moving, as seen by the others.
+pub type Neighbour = (
+    Vec2,
+    Vec2,
+);
+
+impl Motion {
+    pub fn new(behavior: Behavior) -> Self {
+        Self {
+            behavior,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Turn towards the velocity the behavior wants at `position`,
+    /// `delta` seconds after the last turn, and return it.
+    pub fn steer(
+        &mut self,
+        position: Vec2,
+        neighbours: &[Neighbour],
+        cars: &[Vec2],
+        rng: &mut impl Rng,
+        delta: f32,
+    ) -> Vec2 {
+        let desired = self
+            .flee(
+                position, cars,
+            )
+            .or_else(|| {
+                self.chase(
+                    position, cars,
+                )
+            })
+            .unwrap_or_else(|| {
+                self.wander(
+                    position, neighbours, rng, delta,
+                )
+            });
+        self.velocity += (desired - self.velocity)
+            * (STEER_RATE * delta).min(1.0);
+        self.velocity
+    }
+
+    /// Away from the nearest car in reach, if there is one.
+    fn flee(
+        &self,
+        position: Vec2,
+        cars: &[Vec2],
+    ) -> Option<Vec2> {
+        let Behavior {
+            flee_radius,
+            flee_speed,
+            ..
+        } = self.behavior;
+        let towards = nearest(
+            position,
+            cars,
+            flee_radius,
+        )?;
+        Some(
+            (-towards)
+                .try_normalize()
+                .unwrap_or(Vec2::X)
+                * flee_speed,
+        )
+    }
+
+    /// Towards the nearest car in reach, if there is one.
+    fn chase(
+        &self,
+        position: Vec2,
+        cars: &[Vec2],
+    ) -> Option<Vec2> {
+        let Behavior {
+            chase_radius,
+            chase_speed,
+            ..
+        } = self.behavior;
+        let towards = nearest(
+            position,
+            cars,
+            chase_radius,
+        )?;
+        Some(towards.normalize_or_zero() * chase_speed)
+    }
+
+    /// Along a drifting heading, bent towards the flock.
+    fn wander(
+        &self,
+        position: Vec2,
+        neighbours: &[Neighbour],
+        rng: &mut impl Rng,
+        delta: f32,
+    ) -> Vec2 {
+        let Behavior { wander, flock, .. } = self.behavior;
+        if wander <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let heading = match self
+            .velocity
+            .try_normalize()
+        {
+            Some(direction) => {
+                direction
+                    .y
+                    .atan2(direction.x)
+                    + rng.gen_range(-1.0..=1.0)
+                        * WANDER_TURN
+                        * delta
+            }
+            None => rng.gen_range(-PI..PI),
+        };
+        let mut desired = Vec2::from_angle(heading);
+        if flock > 0.0 {
+            if let Some(flocking) = flocking(
+                position, neighbours,
+            ) {
+                desired = desired.lerp(
+                    flocking,
+                    flock.min(1.0),
+                );
+            }
+        }
+        desired.normalize_or_zero() * wander
+    }
+}
+
+/// From `position` to the nearest of `cars` closer than `radius`.
+fn nearest(
+    position: Vec2,
+    cars: &[Vec2],
+    radius: f32,
+) -> Option<Vec2> {
+    cars.iter()
+        .map(|&car| car - position)
+        .filter(|towards| towards.length() < radius)
+        .min_by(
+            |a, b| {
+                a.length()
+                    .total_cmp(&b.length())
+            },
+        )
+}
+
+/// Direction that keeps a feris at `position` with `neighbours` in
+/// reach: towards their middle, along their way, and away from any
+/// too close. `None` if there are none in reach.
+fn flocking(
+    position: Vec2,
+    neighbours: &[Neighbour],
+) -> Option<Vec2> {
+    let mut count = 0;
+    let mut centre = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut apart = Vec2::ZERO;
+    for &(other, velocity) in neighbours {
+        let offset = position - other;
+        let distance = offset.length();
+        // Itself, or one in exactly the same place
+        if distance == 0.0 || distance > FLOCK_RADIUS {
+            continue;
+        }
+        count += 1;
+        centre += other;
+        heading += velocity.normalize_or_zero();
+        if distance < SEPARATION {
+            apart += offset / distance
+                * (SEPARATION - distance)
+                / SEPARATION;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+    let count = count as f32;
+    let towards =
+        (centre / count - position) / FLOCK_RADIUS;
+    Some(
+        (towards + heading / count + 2.0 * apart)
+            .normalize_or_zero(),
+    )
+}
+
+/// Keep a feris of `radius` at `position` inside a `window` centred on
+/// the origin, turning it back from the edges.
+pub fn confine(
+    position: &mut Vec2,
+    velocity: &mut Vec2,
+    radius: f32,
+    window: Vec2,
+) {
+    let limit = (window / 2.0 - Vec2::splat(radius))
+        .max(Vec2::ZERO);
+    for axis in 0..2 {
+        if position[axis].abs() > limit[axis] {
+            position[axis] = position[axis].clamp(
+                -limit[axis],
+                limit[axis],
+            );
+            velocity[axis] = -position[axis].signum()
+                * velocity[axis].abs();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FRAME: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn feris_run_from_cars_in_reach() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut motion = Motion::new(
+            Behavior {
+                flee_radius: 100.0,
+                flee_speed: 200.0,
+                ..Default::default()
+            },
+        );
+        let car = Vec2::new(
+            -50.0, 0.0,
+        );
+        for _ in 0..60 {
+            motion.steer(
+                Vec2::ZERO,
+                &[],
+                &[car],
+                &mut rng,
+                FRAME,
+            );
+        }
+        assert!(
+            motion
+                .velocity
+                .x
+                > 190.0
+        );
+        assert!(
+            motion
+                .velocity
+                .y
+                .abs()
+                < 0.001
+        );
+
+        // Out of reach it comes to a stop
+        let far = Vec2::new(
+            -500.0, 0.0,
+        );
+        for _ in 0..120 {
+            motion.steer(
+                Vec2::ZERO,
+                &[],
+                &[far],
+                &mut rng,
+                FRAME,
+            );
+        }
+        assert!(
+            motion
+                .velocity
+                .length()
+                < 1.0
+        );
+    }
+
+    #[test]
+    fn feris_chase_cars_until_close_enough_to_flee() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut motion = Motion::new(
+            Behavior {
+                flee_radius: 50.0,
+                flee_speed: 200.0,
+                chase_radius: 300.0,
+                chase_speed: 100.0,
+                ..Default::default()
+            },
+        );
+        let steer = |motion: &mut Motion,
+                     rng: &mut StdRng,
+                     car: Vec2| {
+            for _ in 0..120 {
+                motion.steer(
+                    Vec2::ZERO,
+                    &[],
+                    &[car],
+                    rng,
+                    FRAME,
+                );
+            }
+        };
+        steer(
+            &mut motion,
+            &mut rng,
+            Vec2::new(
+                0.0, 200.0,
+            ),
+        );
+        assert!(
+            motion
+                .velocity
+                .y
+                > 95.0
+        );
+        steer(
+            &mut motion,
+            &mut rng,
+            Vec2::new(
+                0.0, 30.0,
+            ),
+        );
+        assert!(
+            motion
+                .velocity
+                .y
+                < -190.0
+        );
+        // Out of reach it comes to a stop
+        steer(
+            &mut motion,
+            &mut rng,
+            Vec2::new(
+                0.0, 400.0,
+            ),
+        );
+        assert!(
+            motion
+                .velocity
+                .length()
+                < 1.0
+        );
+    }
+
+    #[test]
+    fn wandering_keeps_its_speed_and_flocks_keep_together()
+    {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut motion = Motion::new(
+            Behavior {
+                wander: 60.0,
+                flock: 1.0,
+                ..Default::default()
+            },
+        );
+        for _ in 0..120 {
+            motion.steer(
+                Vec2::ZERO,
+                &[],
+                &[],
+                &mut rng,
+                FRAME,
+            );
+        }
+        assert!(
+            (motion
+                .velocity
+                .length()
+                - 60.0)
+                .abs()
+                < 5.0
+        );
+
+        // A flock off to the right pulls it that way
+        let flock = [
+            (
+                Vec2::new(
+                    100.0, 10.0,
+                ),
+                Vec2::ZERO,
+            ),
+            (
+                Vec2::new(
+                    100.0, -10.0,
+                ),
+                Vec2::ZERO,
+            ),
+        ];
+        for _ in 0..120 {
+            motion.steer(
+                Vec2::ZERO,
+                &flock,
+                &[],
+                &mut rng,
+                FRAME,
+            );
+        }
+        assert!(
+            motion
+                .velocity
+                .x
+                > 40.0
+        );
+    }
+
+    #[test]
+    fn feris_stay_on_screen() {
+        let mut position = Vec2::new(
+            700.0, 0.0,
+        );
+        let mut velocity = Vec2::new(
+            50.0, 10.0,
+        );
+        confine(
+            &mut position,
+            &mut velocity,
+            10.0,
+            Vec2::new(
+                1280.0, 720.0,
+            ),
+        );
+        assert_eq!(
+            position,
+            Vec2::new(630.0, 0.0)
+        );
+        assert_eq!(
+            velocity,
+            Vec2::new(-50.0, 10.0)
+        );
+    }
+}
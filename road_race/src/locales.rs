@@ -0,0 +1,53 @@
+//! The languages the game's text is translated into.
+
+use locale::Locale;
+
+/// Each translation with its language, English first as the fallback
+/// for the others.
+const LOCALES: &[(
+    &str,
+    &str,
+)] = &[
+    (
+        "en",
+        include_str!("../locales/en.toml"),
+    ),
+    (
+        "de",
+        include_str!("../locales/de.toml"),
+    ),
+];
+
+/// The translation for `language`, or English.
+pub fn select(language: Option<&str>) -> Locale {
+    locale::select(
+        language, LOCALES,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn translations_match_english() {
+        let english = Locale::from_toml(
+            "en",
+            LOCALES[0].1,
+        )
+        .unwrap();
+        for &(language, text) in &LOCALES[1..] {
+            let locale = Locale::from_toml(
+                language, text,
+            )
+            .unwrap();
+            assert!(
+                english
+                    .keys()
+                    .eq(locale.keys()),
+                "{} has different keys from en",
+                language
+            );
+        }
+    }
+}
@@ -1,10 +1,129 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use entities::{Kind, Registry};
+use key_bindings::{key_name, Action, KeyBindings};
+use locale::Locale;
+use log::warn;
 use rand::prelude::*;
 use rusty_engine::prelude::*;
 
+mod locales;
+mod settings;
+mod speed;
+mod storage;
+use settings::Settings;
+use speed::SpeedCurve;
+
+/// Key bindings file inside the `road_race` directory of the user
+/// config directory.
+const KEY_BINDINGS_FILE: &str = "key_bindings.toml";
+/// Speed curve file inside the `road_race` directory of the user
+/// config directory.
+const SPEED_FILE: &str = "speed.toml";
+/// Settings file inside the `road_race` directory of the user config
+/// directory.
+const SETTINGS_FILE: &str = "settings.toml";
+/// How fast the car moves up and down at the starting road speed, in
+/// pixels per second. It steers faster as the road speeds up.
+const MOVEMENT_SPEED: f32 = 700.0;
+/// Obstacles on the road at the starting road speed. There are more as
+/// the road speeds up.
+const OBSTACLES: f32 = 3.0;
+/// Most obstacles on the road at once, however fast it goes.
+const MAX_OBSTACLES: usize = 12;
+/// Sprites the obstacles take turns using.
+const OBSTACLE_PRESETS: [SpritePreset; 3] = [
+    SpritePreset::RacingBarrelRed,
+    SpritePreset::RacingBarrelBlue,
+    SpritePreset::RacingConeStraight,
+];
+/// Best distance file inside the `road_race` directory of the user
+/// data directory.
+const BEST_DISTANCE_FILE: &str = "best_distance";
+/// Pixels of road to a metre of distance.
+const PIXELS_PER_METRE: f32 = 50.0;
+/// Where the car starts each run.
+const PLAYER_START: Vec2 = Vec2::new(
+    -500.0, 0.0,
+);
+/// Layer the car is drawn on, above the road and everything on it.
+const PLAYER_LAYER: f32 = 10.0;
+/// Layer behind the camera's view, where the blinking car is out of
+/// sight while its collider stays as it is.
+const HIDDEN_LAYER: f32 = -1.0;
+/// Seconds the car stays shown or hidden while blinking.
+const BLINK_SECONDS: f32 = 0.1;
+/// Health at the start of each run.
+const START_HEALTH: u8 = 10;
+/// Music played during a run.
+const MUSIC: MusicPreset = MusicPreset::WhimsicalPopsicle;
+const MUSIC_VOLUME: f32 = 0.2;
+/// Texts of the game over screen, removed on restarting.
+const GAME_OVER_TEXTS: [&str; 3] =
+    ["game_over", "game_over_result", "play_again"];
+
+/// What each sprite is.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+enum Entity {
+    Player,
+    Roadline,
+    Obstacle,
+    /// Gives back some health.
+    RepairKit,
+    /// Takes the next hit instead of the car.
+    Shield,
+}
+
+impl Kind for Entity {
+    const ALL: &'static [Self] = &[
+        Entity::Player,
+        Entity::Roadline,
+        Entity::Obstacle,
+        Entity::RepairKit,
+        Entity::Shield,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Entity::Player => "player",
+            Entity::Roadline => "roadline",
+            Entity::Obstacle => "obstacle",
+            Entity::RepairKit => "repair_kit",
+            Entity::Shield => "shield",
+        }
+    }
+}
+
 #[derive(Resource)]
 struct GameState {
     health_amount: u8,
     loss: bool,
+    key_bindings: KeyBindings,
+    /// Language of every text shown.
+    locale: Locale,
+    /// Every sprite, labelled by the registry.
+    entities: Registry<Entity>,
+    /// How the road speeds up over a run.
+    speed_curve: SpeedCurve,
+    settings: Settings,
+    /// Counts down the time the car can't be hurt after a hit.
+    invulnerable: Option<Timer>,
+    /// A shield was picked up and will take the next hit.
+    shielded: bool,
+    /// Counts down to the next pickup on the road.
+    pickup_timer: Timer,
+    /// Seconds since the run started.
+    elapsed: f32,
+    /// Metres driven this run.
+    distance: f32,
+    /// Most metres driven in any run.
+    best_distance: f32,
+    /// Where `best_distance` is persisted. `None` disables saving.
+    best_distance_path: Option<PathBuf>,
 }
 
 impl GameState {
@@ -14,42 +133,412 @@ impl GameState {
         health_message: &mut Text,
     ) {
         self.health_amount = new_health_amount;
-        health_message.value = format!(
-            "Health: {}",
-            self.health_amount
-        );
+        health_message.value = self
+            .locale
+            .format(
+                "health",
+                &[(
+                    "health",
+                    &self.health_amount,
+                )],
+            );
     }
+
+    /// Take a health for a hit, unless the car is still invulnerable
+    /// from the last one or has a shield to take it.
+    fn hit(&mut self, health_message: &mut Text) -> Hit {
+        if self
+            .invulnerable
+            .is_some()
+            || self.health_amount == 0
+        {
+            return Hit::Ignored;
+        }
+        let hit = if self.shielded {
+            self.shielded = false;
+            Hit::Absorbed
+        } else {
+            self.update_health_amount(
+                self.health_amount - 1,
+                health_message,
+            );
+            Hit::Hurt
+        };
+        // Either way, the obstacles around it can't hurt it for now
+        let seconds = self
+            .settings
+            .invulnerable_seconds;
+        if seconds > 0.0 {
+            self.invulnerable = Some(
+                Timer::from_seconds(
+                    seconds,
+                    TimerMode::Once,
+                ),
+            );
+        }
+        hit
+    }
+
+    /// Give the car what the pickup `kind` holds.
+    fn pick_up(
+        &mut self,
+        kind: Entity,
+        health_message: &mut Text,
+    ) {
+        match kind {
+            Entity::RepairKit => {
+                let health = self
+                    .health_amount
+                    .saturating_add(
+                        self.settings
+                            .repair_health,
+                    )
+                    .min(
+                        self.settings
+                            .max_health,
+                    )
+                    .max(self.health_amount);
+                self.update_health_amount(
+                    health,
+                    health_message,
+                );
+            }
+            Entity::Shield => self.shielded = true,
+            Entity::Player
+            | Entity::Roadline
+            | Entity::Obstacle => {}
+        }
+    }
+
+    /// What the HUD shows about the shield.
+    fn shield_text(&self) -> String {
+        if self.shielded {
+            self.locale
+                .text("shield")
+        } else {
+            String::new()
+        }
+    }
+
+    /// Count down the invulnerability after a hit by `delta` seconds,
+    /// blinking `car` and showing the time left in `message`.
+    fn tick_invulnerable(
+        &mut self,
+        delta: f32,
+        car: &mut Sprite,
+        message: &mut Text,
+    ) {
+        let Some(timer) = &mut self.invulnerable else {
+            return;
+        };
+        timer.tick(Duration::from_secs_f32(delta));
+        if timer.finished() {
+            self.end_invulnerable(
+                car, message,
+            );
+            return;
+        }
+        // Sprites can't be hidden, so the car blinks by dropping out of
+        // view, still picking up whatever it drives over
+        let hidden =
+            ((timer.elapsed_secs() / BLINK_SECONDS) as u32)
+                .is_multiple_of(2);
+        car.layer = if hidden {
+            HIDDEN_LAYER
+        } else {
+            PLAYER_LAYER
+        };
+        message.value = self
+            .locale
+            .format(
+                "invulnerable",
+                &[(
+                    "seconds",
+                    &(timer
+                        .remaining_secs()
+                        .ceil()
+                        as u32),
+                )],
+            );
+    }
+
+    /// Make the car hurtable again and show it for good.
+    fn end_invulnerable(
+        &mut self,
+        car: &mut Sprite,
+        message: &mut Text,
+    ) {
+        self.invulnerable = None;
+        car.layer = PLAYER_LAYER;
+        message
+            .value
+            .clear();
+    }
+
+    /// Add `metres` to the distance driven.
+    fn drive(
+        &mut self,
+        metres: f32,
+        distance_message: &mut Text,
+    ) {
+        self.distance += metres;
+        distance_message.value = self
+            .locale
+            .format(
+                "distance",
+                &[(
+                    "distance",
+                    &(self.distance as u32),
+                )],
+            );
+    }
+
+    /// Text of the game over screen comparing this run with the
+    /// record, keeping it if this run beat it.
+    fn finish_run(&mut self) -> String {
+        let distance = self.distance as u32;
+        if self.distance <= self.best_distance {
+            return self
+                .locale
+                .format(
+                    "result",
+                    &[
+                        (
+                            "distance", &distance,
+                        ),
+                        (
+                            "best",
+                            &(self.best_distance as u32),
+                        ),
+                    ],
+                );
+        }
+        self.best_distance = self.distance;
+        if let Some(path) = &self.best_distance_path {
+            if let Err(e) = save_best_distance(
+                path,
+                self.best_distance,
+            ) {
+                warn!(
+                    "Could not save best distance to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.locale
+            .format(
+                "new_record",
+                &[(
+                    "distance", &distance,
+                )],
+            )
+    }
+}
+
+/// What came of the car running into an obstacle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Hit {
+    /// The car was still invulnerable from the last hit.
+    Ignored,
+    /// The shield took the hit.
+    Absorbed,
+    /// The car lost a health.
+    Hurt,
+}
+
+/// Counts down to the next pickup, as often as `settings` has them.
+fn pickup_timer(settings: &Settings) -> Timer {
+    Timer::from_seconds(
+        settings.pickup_seconds,
+        TimerMode::Repeating,
+    )
+}
+
+/// Put a repair kit or shield, at random, on the road ahead.
+fn spawn_pickup(
+    engine: &mut Engine,
+    game_state: &mut GameState,
+) {
+    let (kind, preset) = if thread_rng().gen_bool(0.5) {
+        (
+            Entity::RepairKit,
+            SpritePreset::RollingBallRed,
+        )
+    } else {
+        (
+            Entity::Shield,
+            SpritePreset::RollingBallBlue,
+        )
+    };
+    let pickup = engine.add_sprite(
+        game_state
+            .entities
+            .spawn(kind)
+            .label(),
+        preset,
+    );
+    pickup.layer = 5.0;
+    pickup.collision = true;
+    place_ahead(pickup);
+}
+
+/// Obstacles on the road when it goes `speed_up` times as fast as it
+/// started.
+fn obstacle_count(speed_up: f32) -> usize {
+    ((OBSTACLES * speed_up).round() as usize)
+        .min(MAX_OBSTACLES)
+}
+
+/// Put a new obstacle somewhere on the road ahead, off screen.
+fn spawn_obstacle(
+    engine: &mut Engine,
+    game_state: &mut GameState,
+) {
+    let id = game_state
+        .entities
+        .spawn(Entity::Obstacle);
+    let preset = OBSTACLE_PRESETS
+        [id.number() as usize % OBSTACLE_PRESETS.len()];
+    let obstacle = engine.add_sprite(
+        id.label(),
+        preset,
+    );
+    obstacle.layer = 5.0;
+    obstacle.collision = true;
+    place_ahead(obstacle);
+}
+
+/// Move `obstacle` to a random spot on the road ahead, off screen.
+fn place_ahead(obstacle: &mut Sprite) {
+    obstacle
+        .translation
+        .x = thread_rng().gen_range(800.0..1600.0);
+    obstacle
+        .translation
+        .y = thread_rng().gen_range(-300.0..300.0);
+}
+
+/// The best distance saved at `path`, or 0 if there is none yet.
+fn load_best_distance(path: &Path) -> f32 {
+    match fs::read_to_string(path) {
+        Ok(text) => text
+            .trim()
+            .parse::<f32>()
+            .map_err(|e| e.to_string())
+            .and_then(|distance| {
+                if distance.is_finite() && distance >= 0.0 {
+                    Ok(distance)
+                } else {
+                    Err(format!(
+                        "{} is not a distance",
+                        distance
+                    ))
+                }
+            })
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Ignoring best distance in {}: {}",
+                    path.display(),
+                    e
+                );
+                0.0
+            }),
+        Err(_) => 0.0,
+    }
+}
+
+fn save_best_distance(
+    path: &Path,
+    distance: f32,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        path,
+        distance.to_string(),
+    )
 }
 
 impl Default for GameState {
     fn default() -> Self {
         Self {
-            health_amount: 10,
+            health_amount: START_HEALTH,
             loss: false,
+            key_bindings: KeyBindings::default(),
+            locale: locales::select(None),
+            entities: Registry::new(),
+            speed_curve: SpeedCurve::default(),
+            settings: Settings::default(),
+            invulnerable: None,
+            shielded: false,
+            pickup_timer: pickup_timer(
+                &Settings::default(),
+            ),
+            elapsed: 0.0,
+            distance: 0.0,
+            best_distance: 0.0,
+            best_distance_path: None,
         }
     }
 }
 
 fn main() {
+    env_logger::Builder::from_env(
+        env_logger::Env::default()
+            .default_filter_or("warn"),
+    )
+    .init();
     let mut game = Game::new();
     let mut game_state = GameState::default();
+    // `--lang <language>`, or the language of the environment
+    let args: Vec<String> = std::env::args().collect();
+    let lang = args
+        .iter()
+        .position(|arg| arg == "--lang")
+        .and_then(|i| args.get(i + 1));
+    game_state.locale = locales::select(
+        locale::requested(lang.map(String::as_str))
+            .as_deref(),
+    );
+    game_state.key_bindings = storage::load_config(
+        KEY_BINDINGS_FILE,
+        |path| KeyBindings::load(path),
+    );
+    game_state.speed_curve = storage::load_config(
+        SPEED_FILE,
+        |path| SpeedCurve::load(path),
+    );
+    game_state.settings = storage::load_config(
+        SETTINGS_FILE,
+        |path| Settings::load(path),
+    );
+    game_state.pickup_timer =
+        pickup_timer(&game_state.settings);
+    if let Some(path) = storage::data_file(BEST_DISTANCE_FILE) {
+        game_state.best_distance =
+            load_best_distance(&path);
+        game_state.best_distance_path = Some(path);
+    }
 
     let player = game.add_sprite(
-        "player",
+        game_state
+            .entities
+            .spawn(Entity::Player)
+            .label(),
         SpritePreset::RacingCarBlue,
     );
-    player.translation = Vec2::new(
-        -500.0, 0.0,
-    );
-    player.layer = 10.0;
+    player.translation = PLAYER_START;
+    player.layer = PLAYER_LAYER;
     player.collision = true;
 
     for i in 0..10 {
         let roadline = game.add_sprite(
-            format!(
-                "roadline{}",
-                i
-            ),
+            game_state
+                .entities
+                .spawn(Entity::Roadline)
+                .label(),
             SpritePreset::RacingBarrierWhite,
         );
         roadline.scale = 0.1;
@@ -77,37 +566,64 @@ fn main() {
     health_message.font_size = 128.0;
     health_message.layer = 0.0;
 
+    // distance message, next to the health message
+    let distance_message = game.add_text(
+        "distance_message",
+        "",
+    );
+    game_state.drive(
+        0.0,
+        distance_message,
+    );
+    distance_message
+        .translation
+        .x = 360.0;
+    distance_message
+        .translation
+        .y = 310.0;
+    distance_message.font_size = 64.0;
+    distance_message.layer = 0.0;
+
+    // invulnerability message, under the health message
+    let invulnerable_message = game.add_text(
+        "invulnerable_message",
+        "",
+    );
+    invulnerable_message
+        .translation
+        .x = -360.0;
+    invulnerable_message
+        .translation
+        .y = 230.0;
+    invulnerable_message.font_size = 48.0;
+    invulnerable_message.layer = 0.0;
+
+    // shield message, under the invulnerability message
+    let shield_message = game.add_text(
+        "shield_message",
+        "",
+    );
+    shield_message
+        .translation
+        .x = -360.0;
+    shield_message
+        .translation
+        .y = 170.0;
+    shield_message.font_size = 48.0;
+    shield_message.layer = 0.0;
+
     game.audio_manager
         .play_music(
-            MusicPreset::WhimsicalPopsicle,
-            0.2,
+            MUSIC,
+            MUSIC_VOLUME,
         );
 
     // Create obstacles
-    let obstacle_presets = vec![
-        SpritePreset::RacingBarrelRed,
-        SpritePreset::RacingBarrelBlue,
-        SpritePreset::RacingConeStraight,
-    ];
-    for (i, preset) in obstacle_presets
-        .into_iter()
-        .enumerate()
-    {
-        let obstacle = game.add_sprite(
-            format!(
-                "obstacle{}",
-                i
-            ),
-            preset,
+    for _ in 0..OBSTACLES as usize {
+        spawn_obstacle(
+            &mut game,
+            &mut game_state,
         );
-        obstacle.layer = 5.0;
-        obstacle.collision = true;
-        obstacle
-            .translation
-            .x = thread_rng().gen_range(800.0..1600.0);
-        obstacle
-            .translation
-            .y = thread_rng().gen_range(-300.0..300.0);
     }
 
     game.add_logic(game_logic);
@@ -119,32 +635,75 @@ fn game_logic(
     game_state: &mut GameState,
 ) {
     if game_state.loss {
+        if game_state
+            .key_bindings
+            .just_pressed(
+                &engine.keyboard_state,
+                Action::Start,
+            )
+        {
+            restart(
+                engine, game_state,
+            );
+        }
         return;
     }
 
+    // Everything speeds up together, so the car handles the same
+    game_state.elapsed += engine.delta_f32;
+    let road_speed = game_state
+        .speed_curve
+        .at(game_state.elapsed);
+    let speed_up = road_speed
+        / game_state
+            .speed_curve
+            .start();
+
     let mut direction = 0.0;
-    const MOVEMENT_SPEED: f32 = 700.0;
-    const ROAD_SPEED: f32 = 400.0;
-    if engine
-        .keyboard_state
-        .pressed(KeyCode::W)
+    if game_state
+        .key_bindings
+        .pressed(
+            &engine.keyboard_state,
+            Action::MoveUp,
+        )
     {
         direction += 1.0;
     }
-    if engine
-        .keyboard_state
-        .pressed(KeyCode::R)
+    if game_state
+        .key_bindings
+        .pressed(
+            &engine.keyboard_state,
+            Action::MoveDown,
+        )
     {
         direction -= 1.0;
     }
+    let Some(player_id) = game_state
+        .entities
+        .of_kind(Entity::Player)
+        .next()
+    else {
+        return;
+    };
     let player = engine
         .sprites
-        .get_mut("player")
+        .get_mut(&player_id.label())
         .unwrap();
+    game_state.tick_invulnerable(
+        engine.delta_f32,
+        player,
+        engine
+            .texts
+            .get_mut("invulnerable_message")
+            .unwrap(),
+    );
     player.rotation = direction * 0.15;
     player
         .translation
-        .y += direction * engine.delta_f32 * MOVEMENT_SPEED;
+        .y += direction
+        * engine.delta_f32
+        * MOVEMENT_SPEED
+        * speed_up;
     if player
         .translation
         .y
@@ -164,52 +723,107 @@ fn game_logic(
     }
 
     // move road left
-    for sprite in engine
-        .sprites
-        .values_mut()
+    let obstacles = obstacle_count(speed_up);
+    let mut surplus = game_state
+        .entities
+        .of_kind(Entity::Obstacle)
+        .count()
+        .saturating_sub(obstacles);
+    let mut passed = Vec::new();
+    for id in game_state
+        .entities
+        .iter()
     {
-        if sprite
-            .label
-            .starts_with("roadline")
-        {
-            sprite
-                .translation
-                .x -= ROAD_SPEED * engine.delta_f32;
-            if sprite
-                .translation
-                .x
-                < -675.0
-            {
-                sprite
+        let Some(sprite) = engine
+            .sprites
+            .get_mut(&id.label())
+        else {
+            continue;
+        };
+        if id.kind() == Entity::Player {
+            continue;
+        }
+        sprite
+            .translation
+            .x -= road_speed * engine.delta_f32;
+        match id.kind() {
+            Entity::Roadline => {
+                if sprite
                     .translation
-                    .x += 1500.0
-            };
-        } else if sprite
-            .label
-            .starts_with("obstacle")
-        {
-            sprite
-                .translation
-                .x -= ROAD_SPEED * engine.delta_f32;
-            if sprite
-                .translation
-                .x
-                < -800.0
-            {
-                sprite
+                    .x
+                    < -675.0
+                {
+                    sprite
+                        .translation
+                        .x += 1500.0
+                };
+            }
+            Entity::Obstacle => {
+                if sprite
                     .translation
-                    .x =
-                    thread_rng().gen_range(800.0..1600.0);
-                sprite
+                    .x
+                    < -800.0
+                {
+                    // Obstacles past the car come round again,
+                    // unless the road has slowed down for fewer
+                    if surplus > 0 {
+                        surplus -= 1;
+                        passed.push(id);
+                    } else {
+                        place_ahead(sprite);
+                    }
+                };
+            }
+            // Pickups the car missed are gone for good
+            Entity::RepairKit | Entity::Shield => {
+                if sprite
                     .translation
-                    .y =
-                    thread_rng().gen_range(-300.0..300.0);
-            };
-        };
+                    .x
+                    < -800.0
+                {
+                    passed.push(id);
+                }
+            }
+            Entity::Player => {}
+        }
+    }
+    for id in passed {
+        game_state
+            .entities
+            .remove(id);
+        engine
+            .sprites
+            .remove(&id.label());
+    }
+    let shown = game_state
+        .entities
+        .of_kind(Entity::Obstacle)
+        .count();
+    for _ in shown..obstacles {
+        spawn_obstacle(
+            engine, game_state,
+        );
+    }
+    if game_state
+        .pickup_timer
+        .tick(Duration::from_secs_f32(engine.delta_f32))
+        .just_finished()
+    {
+        spawn_pickup(
+            engine, game_state,
+        );
     }
 
+    game_state.drive(
+        road_speed * engine.delta_f32 / PIXELS_PER_METRE,
+        engine
+            .texts
+            .get_mut("distance_message")
+            .unwrap(),
+    );
+
     // handle collisions
-    let mut health_message = engine
+    let health_message = engine
         .texts
         .get_mut("health_message")
         .unwrap();
@@ -217,37 +831,131 @@ fn game_logic(
         .collision_events
         .drain(..)
     {
-        if !event
-            .pair
-            .either_contains("player")
-            || event
-                .state
-                .is_end()
+        if event
+            .state
+            .is_end()
         {
             continue;
         }
-        if game_state.health_amount > 0 {
-            game_state.update_health_amount(
-                game_state.health_amount - 1,
-                health_message,
+        // What the car ran into, if it was the car
+        let ids = event
+            .pair
+            .array()
+            .map(
+                |label| {
+                    game_state
+                        .entities
+                        .get(label)
+                },
             );
-            engine
-                .audio_manager
-                .play_sfx(
-                    SfxPreset::Impact3,
-                    0.5,
+        let other = match ids {
+            [Some(player), Some(other)]
+            | [Some(other), Some(player)]
+                if player.kind() == Entity::Player =>
+            {
+                other
+            }
+            _ => continue,
+        };
+        match other.kind() {
+            Entity::Obstacle => {
+                match game_state.hit(health_message) {
+                    Hit::Hurt => engine
+                        .audio_manager
+                        .play_sfx(
+                            SfxPreset::Impact3,
+                            0.5,
+                        ),
+                    Hit::Absorbed => engine
+                        .audio_manager
+                        .play_sfx(
+                            SfxPreset::Forcefield2,
+                            0.5,
+                        ),
+                    Hit::Ignored => {}
+                }
+            }
+            Entity::RepairKit | Entity::Shield => {
+                game_state
+                    .entities
+                    .remove(other);
+                engine
+                    .sprites
+                    .remove(&other.label());
+                game_state.pick_up(
+                    other.kind(),
+                    health_message,
                 );
+                engine
+                    .audio_manager
+                    .play_sfx(
+                        SfxPreset::Confirmation1,
+                        0.5,
+                    );
+            }
+            Entity::Player | Entity::Roadline => {}
         }
     }
+    engine
+        .texts
+        .get_mut("shield_message")
+        .unwrap()
+        .value = game_state.shield_text();
 
     // loss condition
-    if game_state.health_amount <= 0 {
+    if game_state.health_amount == 0 {
         game_state.loss = true;
+        // The car may have been mid-blink, and nothing ticks it on
+        // the game over screen
+        if let Some(car) = engine
+            .sprites
+            .get_mut(&player_id.label())
+        {
+            game_state.end_invulnerable(
+                car,
+                engine
+                    .texts
+                    .get_mut("invulnerable_message")
+                    .unwrap(),
+            );
+        }
         let game_over = engine.add_text(
             "game_over",
-            "Game Over!",
+            game_state
+                .locale
+                .text("game_over"),
         );
         game_over.font_size = 256.0;
+        let result = game_state.finish_run();
+        let result = engine.add_text(
+            "game_over_result",
+            result,
+        );
+        result
+            .translation
+            .y = -180.0;
+        result.font_size = 64.0;
+        let key = game_state
+            .key_bindings
+            .keys(Action::Start)
+            .first()
+            .and_then(|&key| key_name(key))
+            .unwrap_or("?");
+        let play_again = engine.add_text(
+            "play_again",
+            game_state
+                .locale
+                .format(
+                    "play_again",
+                    &[(
+                        "key", &key,
+                    )],
+                ),
+        );
+        play_again
+            .translation
+            .y = -260.0;
+        play_again.font_size = 48.0;
         engine
             .audio_manager
             .stop_music();
@@ -259,3 +967,338 @@ fn game_logic(
             );
     }
 }
+
+/// Start a new run after losing the last one.
+fn restart(
+    engine: &mut Engine,
+    game_state: &mut GameState,
+) {
+    game_state.loss = false;
+    game_state.invulnerable = None;
+    game_state.shielded = false;
+    game_state
+        .pickup_timer
+        .reset();
+    game_state.elapsed = 0.0;
+    game_state.distance = 0.0;
+    for label in GAME_OVER_TEXTS {
+        engine
+            .texts
+            .remove(label);
+    }
+    for label in ["invulnerable_message", "shield_message"]
+    {
+        engine
+            .texts
+            .get_mut(label)
+            .unwrap()
+            .value
+            .clear();
+    }
+    // Pickups nobody got are left behind with the last run
+    for kind in [Entity::RepairKit, Entity::Shield] {
+        for id in game_state
+            .entities
+            .remove_kind(kind)
+        {
+            engine
+                .sprites
+                .remove(&id.label());
+        }
+    }
+    game_state.update_health_amount(
+        START_HEALTH,
+        engine
+            .texts
+            .get_mut("health_message")
+            .unwrap(),
+    );
+    game_state.drive(
+        0.0,
+        engine
+            .texts
+            .get_mut("distance_message")
+            .unwrap(),
+    );
+    for id in game_state
+        .entities
+        .iter()
+    {
+        let Some(sprite) = engine
+            .sprites
+            .get_mut(&id.label())
+        else {
+            continue;
+        };
+        match id.kind() {
+            Entity::Player => {
+                sprite.translation = PLAYER_START;
+                sprite.rotation = 0.0;
+                sprite.layer = PLAYER_LAYER;
+            }
+            Entity::Obstacle => place_ahead(sprite),
+            Entity::Roadline
+            | Entity::RepairKit
+            | Entity::Shield => {}
+        }
+    }
+    engine
+        .audio_manager
+        .play_music(
+            MUSIC,
+            MUSIC_VOLUME,
+        );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A run where nothing is invulnerable after a hit.
+    fn without_invulnerability() -> GameState {
+        GameState {
+            settings: Settings {
+                invulnerable_seconds: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shields_take_exactly_one_hit() {
+        let mut state = without_invulnerability();
+        let mut message = Text::default();
+        state.pick_up(
+            Entity::Shield,
+            &mut message,
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Absorbed
+        );
+        assert_eq!(
+            state.health_amount,
+            START_HEALTH
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Hurt
+        );
+        assert_eq!(
+            state.health_amount,
+            START_HEALTH - 1
+        );
+    }
+
+    #[test]
+    fn hits_are_ignored_while_invulnerable() {
+        let mut state = GameState::default();
+        let mut message = Text::default();
+        let mut car = Sprite::new(
+            "player0",
+            SpritePreset::RacingCarBlue,
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Hurt
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Ignored
+        );
+        assert_eq!(
+            state.health_amount,
+            START_HEALTH - 1
+        );
+        // Blinking leaves the car as big as it was
+        state.tick_invulnerable(
+            BLINK_SECONDS / 2.0,
+            &mut car,
+            &mut message,
+        );
+        assert_eq!(
+            car.layer,
+            HIDDEN_LAYER
+        );
+        assert_eq!(
+            car.scale,
+            1.0
+        );
+        state.tick_invulnerable(
+            state
+                .settings
+                .invulnerable_seconds,
+            &mut car,
+            &mut message,
+        );
+        assert_eq!(
+            car.layer,
+            PLAYER_LAYER
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Hurt
+        );
+
+        // Ending it early, as when the run is lost, shows the car
+        state.tick_invulnerable(
+            BLINK_SECONDS / 2.0,
+            &mut car,
+            &mut message,
+        );
+        state.end_invulnerable(
+            &mut car,
+            &mut message,
+        );
+        assert_eq!(
+            car.layer,
+            PLAYER_LAYER
+        );
+        assert!(state
+            .invulnerable
+            .is_none());
+        assert_eq!(
+            message.value,
+            ""
+        );
+    }
+
+    #[test]
+    fn health_stops_at_zero() {
+        let mut state = GameState {
+            health_amount: 1,
+            ..without_invulnerability()
+        };
+        let mut message = Text::default();
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Hurt
+        );
+        assert_eq!(
+            state.hit(&mut message),
+            Hit::Ignored
+        );
+        assert_eq!(
+            state.health_amount,
+            0
+        );
+    }
+
+    #[test]
+    fn repair_kits_heal_up_to_the_most_health() {
+        let mut state = GameState {
+            health_amount: 5,
+            ..Default::default()
+        };
+        let mut message = Text::default();
+        for health in [8, 10, 10] {
+            state.pick_up(
+                Entity::RepairKit,
+                &mut message,
+            );
+            assert_eq!(
+                state.health_amount,
+                health
+            );
+        }
+        assert_eq!(
+            message.value,
+            "Health: 10"
+        );
+        // Health above the most is left alone
+        state.health_amount = 12;
+        state.pick_up(
+            Entity::RepairKit,
+            &mut message,
+        );
+        assert_eq!(
+            state.health_amount,
+            12
+        );
+    }
+
+    #[test]
+    fn obstacles_grow_with_speed_up_to_a_limit() {
+        assert_eq!(
+            obstacle_count(1.0),
+            3
+        );
+        assert_eq!(
+            obstacle_count(2.0),
+            6
+        );
+        // A curve starting at a crawl can't flood the road
+        assert_eq!(
+            obstacle_count(1_000_000.0),
+            MAX_OBSTACLES
+        );
+    }
+
+    #[test]
+    fn records_are_kept_only_when_beaten() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "road-race-record-{}",
+                std::process::id()
+            ),
+        );
+        let mut state = GameState {
+            distance: 80.0,
+            best_distance: 100.0,
+            best_distance_path: Some(path.clone()),
+            ..Default::default()
+        };
+        assert_eq!(
+            state.finish_run(),
+            "You drove 80 m. Record: 100 m"
+        );
+        assert_eq!(
+            state.best_distance,
+            100.0
+        );
+        assert!(!path.exists());
+
+        state.distance = 150.0;
+        assert_eq!(
+            state.finish_run(),
+            "New record: 150 m!"
+        );
+        assert_eq!(
+            state.best_distance,
+            150.0
+        );
+        assert_eq!(
+            load_best_distance(&path),
+            150.0
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn best_distance_files_fall_back_to_zero() {
+        let path = std::env::temp_dir().join(
+            format!(
+                "road-race-corrupt-{}",
+                std::process::id()
+            ),
+        );
+        assert_eq!(
+            load_best_distance(&path),
+            0.0
+        );
+        for text in ["far", "inf", "NaN", "-5"] {
+            fs::write(
+                &path, text,
+            )
+            .unwrap();
+            assert_eq!(
+                load_best_distance(&path),
+                0.0,
+                "{}",
+                text
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+//! Tuning of a run that isn't part of the speed curve.
+//!
+//! The defaults can be replaced by a `settings.toml` in the
+//! `road_race` config directory setting any of the fields of
+//! [`Settings`]:
+//!
+//! ```toml
+//! invulnerable_seconds = 2.0
+//! pickup_seconds = 5.0
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("could not read settings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid settings: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("{0} must be a finite number")]
+    NotFinite(&'static str),
+    #[error("{0} must not be negative")]
+    Negative(&'static str),
+    #[error("pickup_seconds must be more than 0")]
+    NoPickupTime,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Seconds the car can't be hurt after a hit.
+    pub invulnerable_seconds: f32,
+    /// Seconds between repair kits or shields turning up on the road.
+    pub pickup_seconds: f32,
+    /// Health a repair kit gives back.
+    pub repair_health: u8,
+    /// Most health a repair kit brings the car up to.
+    pub max_health: u8,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            invulnerable_seconds: 1.5,
+            pickup_seconds: 8.0,
+            repair_health: 3,
+            max_health: 10,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, SettingsError> {
+        let settings: Self =
+            toml::from_str(&fs::read_to_string(path)?)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), SettingsError> {
+        // Both go into timers, which panic on anything else
+        for (name, seconds) in [
+            (
+                "invulnerable_seconds",
+                self.invulnerable_seconds,
+            ),
+            (
+                "pickup_seconds",
+                self.pickup_seconds,
+            ),
+        ] {
+            if !seconds.is_finite() {
+                return Err(SettingsError::NotFinite(name));
+            }
+        }
+        if self.invulnerable_seconds < 0.0 {
+            return Err(
+                SettingsError::Negative(
+                    "invulnerable_seconds",
+                ),
+            );
+        }
+        if self.pickup_seconds <= 0.0 {
+            return Err(SettingsError::NoPickupTime);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn settings_left_out_keep_their_defaults() {
+        let settings: Settings =
+            toml::from_str("").unwrap();
+        assert_eq!(
+            settings,
+            Settings::default()
+        );
+        let settings: Settings =
+            toml::from_str("invulnerable_seconds = -1.0")
+                .unwrap();
+        assert!(
+            matches!(
+                settings.validate(),
+                Err(
+                    SettingsError::Negative(
+                        "invulnerable_seconds"
+                    )
+                )
+            )
+        );
+        assert!(
+            toml::from_str::<Settings>("lives = 3")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn seconds_must_be_usable_by_timers() {
+        let validate = |toml: &str| {
+            toml::from_str::<Settings>(toml)
+                .unwrap()
+                .validate()
+        };
+        for (toml, field) in [
+            (
+                "invulnerable_seconds = nan",
+                "invulnerable_seconds",
+            ),
+            (
+                "invulnerable_seconds = inf",
+                "invulnerable_seconds",
+            ),
+            (
+                "pickup_seconds = nan",
+                "pickup_seconds",
+            ),
+            (
+                "pickup_seconds = inf",
+                "pickup_seconds",
+            ),
+        ] {
+            assert!(
+                matches!(
+                    validate(toml),
+                    Err(SettingsError::NotFinite(name))
+                        if name == field
+                ),
+                "{}",
+                toml
+            );
+        }
+        assert!(
+            matches!(
+                validate("pickup_seconds = 0.0"),
+                Err(SettingsError::NoPickupTime)
+            )
+        );
+        assert!(
+            validate("invulnerable_seconds = 0.0").is_ok()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! How fast the road goes as a run wears on.
+//!
+//! The road speeds up along a curve of points, each a number of
+//! seconds into the run and the road speed in pixels per second at
+//! that time. Between points the speed changes evenly, and after the
+//! last one it stays put. The default curve can be replaced by a
+//! `speed.toml` in the `road_race` config directory:
+//!
+//! ```toml
+//! points = [[0.0, 400.0], [60.0, 700.0], [180.0, 1000.0]]
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SpeedError {
+    #[error("could not read speed curve: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid speed curve: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("the speed curve must start at 0 seconds")]
+    NoStart,
+    #[error("point {0} of the speed curve is before the one ahead of it")]
+    Unordered(usize),
+    #[error("point {0} of the speed curve is not faster than standing still")]
+    NotMoving(usize),
+    #[error("point {0} of the speed curve is not a finite number")]
+    NotFinite(usize),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeedCurve {
+    /// Seconds into the run and the road speed then, in order.
+    points: Vec<(
+        f32,
+        f32,
+    )>,
+}
+
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        Self {
+            points: vec![
+                (
+                    0.0, 400.0,
+                ),
+                (
+                    30.0, 550.0,
+                ),
+                (
+                    90.0, 750.0,
+                ),
+                (
+                    180.0, 950.0,
+                ),
+            ],
+        }
+    }
+}
+
+impl SpeedCurve {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, SpeedError> {
+        let curve: Self =
+            toml::from_str(&fs::read_to_string(path)?)?;
+        curve.validate()?;
+        Ok(curve)
+    }
+
+    fn validate(&self) -> Result<(), SpeedError> {
+        if let Some(i) = self
+            .points
+            .iter()
+            .position(
+                |&(seconds, speed)| {
+                    !seconds.is_finite()
+                        || !speed.is_finite()
+                },
+            )
+        {
+            return Err(SpeedError::NotFinite(i));
+        }
+        if self
+            .points
+            .first()
+            .map(|&(seconds, _)| seconds)
+            != Some(0.0)
+        {
+            return Err(SpeedError::NoStart);
+        }
+        for (i, pair) in self
+            .points
+            .windows(2)
+            .enumerate()
+        {
+            if pair[1].0 < pair[0].0 {
+                return Err(SpeedError::Unordered(i + 1));
+            }
+        }
+        if let Some(i) = self
+            .points
+            .iter()
+            .position(|&(_, speed)| speed <= 0.0)
+        {
+            return Err(SpeedError::NotMoving(i));
+        }
+        Ok(())
+    }
+
+    /// Road speed at the start of a run.
+    pub fn start(&self) -> f32 {
+        self.at(0.0)
+    }
+
+    /// Road speed `seconds` into a run, in pixels per second.
+    pub fn at(&self, seconds: f32) -> f32 {
+        let next = self
+            .points
+            .iter()
+            .position(|&(time, _)| time > seconds)
+            .unwrap_or(
+                self.points
+                    .len(),
+            );
+        match (
+            next.checked_sub(1),
+            self.points
+                .get(next),
+        ) {
+            (Some(i), Some(&(to_time, to_speed))) => {
+                let (from_time, from_speed) =
+                    self.points[i];
+                let progress = (seconds - from_time)
+                    / (to_time - from_time);
+                from_speed
+                    + (to_speed - from_speed) * progress
+            }
+            (Some(i), None) => self.points[i].1,
+            (None, _) => self
+                .points
+                .first()
+                .map_or(
+                    0.0,
+                    |&(_, speed)| speed,
+                ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn speed_follows_the_curve() {
+        let curve = SpeedCurve {
+            points: vec![
+                (
+                    0.0, 400.0,
+                ),
+                (
+                    10.0, 600.0,
+                ),
+                (
+                    10.0, 800.0,
+                ),
+                (
+                    20.0, 1000.0,
+                ),
+            ],
+        };
+        assert_eq!(
+            curve.start(),
+            400.0
+        );
+        assert_eq!(
+            curve.at(5.0),
+            500.0
+        );
+        // A jump where two points share a time
+        assert_eq!(
+            curve.at(10.0),
+            800.0
+        );
+        assert_eq!(
+            curve.at(15.0),
+            900.0
+        );
+        assert_eq!(
+            curve.at(60.0),
+            1000.0
+        );
+        assert!(curve
+            .validate()
+            .is_ok());
+
+        let backwards = SpeedCurve {
+            points: vec![
+                (
+                    0.0, 400.0,
+                ),
+                (
+                    10.0, 600.0,
+                ),
+                (
+                    5.0, 800.0,
+                ),
+            ],
+        };
+        assert!(
+            matches!(
+                backwards.validate(),
+                Err(SpeedError::Unordered(2))
+            )
+        );
+        assert!(
+            matches!(
+                SpeedCurve { points: vec![] }.validate(),
+                Err(SpeedError::NoStart)
+            )
+        );
+        for point in [
+            (
+                10.0,
+                f32::NAN,
+            ),
+            (
+                10.0,
+                f32::INFINITY,
+            ),
+            (
+                f32::INFINITY,
+                800.0,
+            ),
+        ] {
+            let curve = SpeedCurve {
+                points: vec![
+                    (
+                        0.0, 400.0,
+                    ),
+                    point,
+                ],
+            };
+            assert!(
+                matches!(
+                    curve.validate(),
+                    Err(SpeedError::NotFinite(1))
+                ),
+                "{:?}",
+                point
+            );
+        }
+        assert!(
+            matches!(
+                SpeedCurve {
+                    points: vec![(0.0, -5.0)]
+                }
+                .validate(),
+                Err(SpeedError::NotMoving(0))
+            )
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+/// Name of the game's directory inside the user data and config
+/// directories.
+const APP_DIR: &str = "road_race";
+
+/// Path of `file_name` inside the game's directory in the user data
+/// directory, or `None` if the platform has no such directory.
+pub fn data_file(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(
+        |dir| {
+            dir.join(APP_DIR)
+                .join(file_name)
+        },
+    )
+}
+
+/// Path of `file_name` inside the game's directory in the user config
+/// directory, or `None` if the platform has no such directory.
+pub fn config_file(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(
+        |dir| {
+            dir.join(APP_DIR)
+                .join(file_name)
+        },
+    )
+}
+
+/// Load `file_name` from the user config directory with `load`. A
+/// missing file gives the defaults, and a file that can't be used is
+/// reported with a warning before falling back to the defaults.
+pub fn load_config<T: Default, E: Display>(
+    file_name: &str,
+    load: impl FnOnce(&Path) -> Result<T, E>,
+) -> T {
+    let Some(path) = config_file(file_name) else {
+        return T::default();
+    };
+    if !path.exists() {
+        return T::default();
+    }
+    load(&path).unwrap_or_else(|e| {
+        warn!(
+            "{}: {}, using the defaults",
+            path.display(),
+            e
+        );
+        T::default()
+    })
+}
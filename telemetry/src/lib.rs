@@ -0,0 +1,407 @@
+//! Gameplay telemetry as JSON Lines, and summaries of it for tuning
+//! the difficulty.
+//!
+//! Every line of a telemetry file is one [`Record`], such as:
+//!
+//! ```json
+//! {"time":12.5,"event":"feris_spawned","x":-120.0,"y":40.5,"clicked":false}
+//! {"time":13.9,"event":"feris_collected","player":0,"age":1.4,"points":1,"score":6}
+//! ```
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("could not read telemetry: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid telemetry on line {line}: {source}")]
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// Something that happened `time` seconds after the game started.
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+pub struct Record {
+    pub time: f32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(
+    Clone, Debug, Deserialize, Serialize, PartialEq,
+)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RoundStarted,
+    /// The round in progress was thrown away and started over.
+    Reset,
+    /// A feris appeared at `x`, `y`, placed by a click if `clicked`.
+    FerisSpawned {
+        x: f32,
+        y: f32,
+        clicked: bool,
+    },
+    /// Player `player` collected a feris `age` seconds after it
+    /// appeared.
+    FerisCollected {
+        player: usize,
+        age: f32,
+        points: u32,
+        score: u32,
+    },
+    /// A feris expired uncollected.
+    FerisMissed,
+    PowerUpCollected {
+        player: usize,
+        power_up: String,
+    },
+    /// The high score went up to `score`.
+    HighScore {
+        score: u32,
+    },
+    RoundEnded {
+        scores: Vec<u32>,
+    },
+    /// Timing of the `frames` frames since the last frame stats.
+    FrameStats {
+        frames: u32,
+        mean_ms: f32,
+        max_ms: f32,
+    },
+}
+
+/// Writes records as JSON Lines.
+pub struct Writer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(
+        &mut self,
+        record: &Record,
+    ) -> io::Result<()> {
+        serde_json::to_writer(
+            &mut self.writer,
+            record,
+        )?;
+        self.writer
+            .write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer
+            .flush()
+    }
+}
+
+/// Read every record from `reader`, skipping blank lines.
+pub fn read<R: BufRead>(
+    reader: R,
+) -> Result<Vec<Record>, TelemetryError> {
+    let mut records = Vec::new();
+    for (i, line) in reader
+        .lines()
+        .enumerate()
+    {
+        let line = line?;
+        if line
+            .trim()
+            .is_empty()
+        {
+            continue;
+        }
+        records.push(
+            serde_json::from_str(&line).map_err(
+                |source| TelemetryError::Json {
+                    line: i + 1,
+                    source,
+                },
+            )?,
+        );
+    }
+    Ok(records)
+}
+
+/// Totals and averages over a whole telemetry file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// Seconds between the first and last record.
+    pub seconds: f32,
+    pub rounds: u32,
+    pub resets: u32,
+    pub spawned: u32,
+    pub collected: u32,
+    pub missed: u32,
+    pub power_ups: u32,
+    /// Total seconds between feris appearing and being collected.
+    pub reaction_seconds: f32,
+    pub high_score: Option<u32>,
+    pub frames: u32,
+    /// Total milliseconds of all `frames`.
+    pub frame_ms: f32,
+    pub max_frame_ms: f32,
+}
+
+impl Summary {
+    pub fn new(records: &[Record]) -> Self {
+        let mut summary = Self {
+            seconds: match (
+                records.first(),
+                records.last(),
+            ) {
+                (Some(first), Some(last)) => {
+                    last.time - first.time
+                }
+                _ => 0.0,
+            },
+            ..Self::default()
+        };
+        for record in records {
+            match &record.event {
+                Event::RoundStarted => summary.rounds += 1,
+                Event::Reset => summary.resets += 1,
+                Event::FerisSpawned { .. } => {
+                    summary.spawned += 1
+                }
+                Event::FerisCollected { age, .. } => {
+                    summary.collected += 1;
+                    summary.reaction_seconds += age;
+                }
+                Event::FerisMissed => summary.missed += 1,
+                Event::PowerUpCollected { .. } => {
+                    summary.power_ups += 1
+                }
+                Event::HighScore { score } => {
+                    summary.high_score = summary
+                        .high_score
+                        .max(Some(
+                            *score,
+                        ))
+                }
+                Event::RoundEnded { .. } => {}
+                Event::FrameStats {
+                    frames,
+                    mean_ms,
+                    max_ms,
+                } => {
+                    summary.frames += frames;
+                    summary.frame_ms +=
+                        mean_ms * *frames as f32;
+                    summary.max_frame_ms = summary
+                        .max_frame_ms
+                        .max(*max_ms);
+                }
+            }
+        }
+        summary
+    }
+
+    /// Average seconds from a feris appearing to it being collected.
+    pub fn reaction_time(&self) -> Option<f32> {
+        (self.collected > 0).then(|| {
+            self.reaction_seconds / self.collected as f32
+        })
+    }
+
+    /// Feris collected per minute of telemetry.
+    pub fn collection_rate(&self) -> Option<f32> {
+        (self.seconds > 0.0).then(|| {
+            self.collected as f32 * 60.0 / self.seconds
+        })
+    }
+
+    pub fn mean_frame_ms(&self) -> Option<f32> {
+        (self.frames > 0)
+            .then(|| self.frame_ms / self.frames as f32)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self
+            .seconds
+            .round() as u32;
+        writeln!(
+            f,
+            "Duration: {}:{:02}",
+            seconds / 60,
+            seconds % 60
+        )?;
+        writeln!(
+            f,
+            "Rounds: {} ({} reset)",
+            self.rounds, self.resets
+        )?;
+        writeln!(
+            f,
+            "Feris: {} spawned, {} collected, {} missed",
+            self.spawned, self.collected, self.missed
+        )?;
+        writeln!(
+            f,
+            "Power-ups collected: {}",
+            self.power_ups
+        )?;
+        if let Some(reaction_time) = self.reaction_time() {
+            writeln!(
+                f,
+                "Average reaction time: {:.2}s",
+                reaction_time
+            )?;
+        }
+        if let Some(rate) = self.collection_rate() {
+            writeln!(
+                f,
+                "Collected per minute: {:.1}",
+                rate
+            )?;
+        }
+        if let Some(high_score) = self.high_score {
+            writeln!(
+                f,
+                "Highest score: {}",
+                high_score
+            )?;
+        }
+        if let Some(mean_frame_ms) = self.mean_frame_ms() {
+            writeln!(
+                f,
+                "Frame time: {:.1}ms mean, {:.1}ms worst",
+                mean_frame_ms, self.max_frame_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(time: f32, event: Event) -> Record {
+        Record { time, event }
+    }
+
+    #[test]
+    fn records_round_trip_as_json_lines() {
+        let records = vec![
+            record(
+                0.0,
+                Event::RoundStarted,
+            ),
+            record(
+                1.5,
+                Event::FerisSpawned {
+                    x: -120.0,
+                    y: 40.5,
+                    clicked: false,
+                },
+            ),
+            record(
+                2.0,
+                Event::Reset,
+            ),
+            record(
+                3.0,
+                Event::RoundEnded { scores: vec![4, 2] },
+            ),
+        ];
+        let mut writer = Writer::new(Vec::new());
+        for record in &records {
+            writer
+                .write(record)
+                .unwrap();
+        }
+        let text =
+            String::from_utf8(writer.writer).unwrap();
+        let started =
+            "{\"time\":0.0,\"event\":\"round_started\"}\n";
+        assert!(text.starts_with(started));
+        assert_eq!(
+            read(text.as_bytes()).unwrap(),
+            records
+        );
+
+        let broken = format!(
+            "{}\nnonsense\n",
+            started
+        );
+        assert!(
+            matches!(
+                read(broken.as_bytes()),
+                Err(TelemetryError::Json { line: 3, .. })
+            )
+        );
+    }
+
+    #[test]
+    fn summaries_average_reactions_and_rates() {
+        let collected = |time, age| {
+            record(
+                time,
+                Event::FerisCollected {
+                    player: 0,
+                    age,
+                    points: 1,
+                    score: 1,
+                },
+            )
+        };
+        let summary = Summary::new(&[
+            record(
+                0.0,
+                Event::RoundStarted,
+            ),
+            collected(
+                10.0, 1.0,
+            ),
+            collected(
+                20.0, 2.0,
+            ),
+            record(
+                25.0,
+                Event::FerisMissed,
+            ),
+            record(
+                30.0,
+                Event::FrameStats {
+                    frames: 60,
+                    mean_ms: 16.0,
+                    max_ms: 20.0,
+                },
+            ),
+        ]);
+        assert_eq!(
+            summary.reaction_time(),
+            Some(1.5)
+        );
+        assert_eq!(
+            summary.collection_rate(),
+            Some(4.0)
+        );
+        assert_eq!(
+            summary.mean_frame_ms(),
+            Some(16.0)
+        );
+        assert_eq!(
+            summary.missed,
+            1
+        );
+        assert_eq!(
+            Summary::new(&[]).reaction_time(),
+            None
+        );
+    }
+}
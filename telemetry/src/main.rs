@@ -0,0 +1,39 @@
+//! Summarize a telemetry file written by `--telemetry <file>`:
+//!
+//! ```sh
+//! cargo run -p telemetry -- telemetry.jsonl
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use telemetry::Summary;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: telemetry <file.jsonl>");
+        return ExitCode::FAILURE;
+    };
+    let records = File::open(&path)
+        .map_err(Into::into)
+        .and_then(
+            |file| telemetry::read(BufReader::new(file)),
+        );
+    match records {
+        Ok(records) => {
+            print!(
+                "{}",
+                Summary::new(&records)
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "{}: {}",
+                path, e
+            );
+            ExitCode::FAILURE
+        }
+    }
+}